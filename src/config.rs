@@ -0,0 +1,403 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use zellij_tile::prelude::*;
+
+use crate::rules::Rule;
+use crate::strings;
+use crate::{Action, BorderStyle, Button, FocusAfterClose, FocusTabAfterClose};
+
+/// What a configuration key takes
+enum ValueKind {
+    /// A boolean, as `parse_bool` understands it
+    Bool,
+    /// A duration, as `parse_duration` understands it
+    Duration,
+    /// A whole number
+    Number,
+    /// One of the emphasis color indices `Text` supports
+    Color,
+    /// A comma-separated list of keys
+    Keys,
+    /// A single key, or nothing to disable what it does
+    OptionalKey,
+    /// An action, or a comma-separated chain of them
+    Actions,
+    /// Anything goes
+    Text,
+    /// One of a few words, checked by the function and listed in the problem
+    Choice(fn(&str) -> bool, &'static str),
+}
+
+/// Every configuration key with a fixed name, and what it takes
+const KEYS: &[(&str, ValueKind)] = &[
+    ("action", ValueKind::Actions),
+    ("config_file", ValueKind::Text),
+    ("show_processes", ValueKind::Bool),
+    ("check_git", ValueKind::Bool),
+    ("icons", ValueKind::Bool),
+    ("ascii_only", ValueKind::Bool),
+    ("timeout_seconds", ValueKind::Duration),
+    ("max_width", ValueKind::Number),
+    ("result_seconds", ValueKind::Duration),
+    ("abort_window_seconds", ValueKind::Duration),
+    (
+        "timeout_action",
+        ValueKind::Choice(
+            |value| matches!(value, "confirm" | "cancel"),
+            "confirm or cancel",
+        ),
+    ),
+    (
+        "default_button",
+        ValueKind::Choice(
+            |value| Button::from_config(value).is_some(),
+            "confirm or cancel",
+        ),
+    ),
+    (
+        "border",
+        ValueKind::Choice(
+            |value| BorderStyle::from_config(value).is_some(),
+            "single, rounded, double, ascii or none",
+        ),
+    ),
+    ("title_color", ValueKind::Color),
+    ("warning_color", ValueKind::Color),
+    ("target_color", ValueKind::Color),
+    ("key_hint_color", ValueKind::Color),
+    ("long_running_minutes", ValueKind::Number),
+    ("close_plugin_panes", ValueKind::Bool),
+    ("minimal_permissions", ValueKind::Bool),
+    ("follow_focus", ValueKind::Bool),
+    ("show_help", ValueKind::Bool),
+    ("help_text", ValueKind::Text),
+    ("session_name", ValueKind::Text),
+    ("pipe_name", ValueKind::Text),
+    ("broadcast_message", ValueKind::Text),
+    ("broadcast_payload", ValueKind::Text),
+    ("layout_file", ValueKind::Text),
+    ("confirm_key", ValueKind::Keys),
+    ("cancel_key", ValueKind::Keys),
+    ("vim_keys", ValueKind::Bool),
+    ("double_confirm", ValueKind::Bool),
+    ("any_key_cancels", ValueKind::Bool),
+    ("yn_shortcuts", ValueKind::Bool),
+    ("detach_key", ValueKind::OptionalKey),
+    ("float_key", ValueKind::OptionalKey),
+    ("stack_key", ValueKind::OptionalKey),
+    ("interrupt_key", ValueKind::OptionalKey),
+    ("disconnect_key", ValueKind::OptionalKey),
+    (
+        "focus_after_close",
+        ValueKind::Choice(
+            |value| FocusAfterClose::from_config(value).is_some(),
+            "left, right, up, down, previous or next",
+        ),
+    ),
+    (
+        "focus_tab_after_close",
+        ValueKind::Choice(
+            |value| FocusTabAfterClose::from_config(value).is_some(),
+            "previous, next, first or last",
+        ),
+    ),
+    (
+        "interrupt_scope",
+        ValueKind::Choice(|value| matches!(value, "tab" | "session"), "tab or session"),
+    ),
+    ("last_tab_quits", ValueKind::Bool),
+    ("danger_commands", ValueKind::Text),
+    ("safe_commands", ValueKind::Text),
+    ("skip_tabs", ValueKind::Text),
+    ("escalate_tabs", ValueKind::Number),
+    ("escalate_panes", ValueKind::Number),
+    ("editor_typed_confirm", ValueKind::Bool),
+    ("typed_confirm_word", ValueKind::Text),
+    ("skip_when_held", ValueKind::Bool),
+    ("skip_when_exited", ValueKind::Bool),
+    ("skip_when_idle", ValueKind::Bool),
+];
+
+/// Check every configuration key, returning a line describing each problem, e.g. an unknown key
+/// or a value the plugin would otherwise quietly replace with its default
+pub fn validate(configuration: &BTreeMap<String, String>) -> Vec<String> {
+    configuration
+        .iter()
+        .filter_map(|(key, value)| key_problem(key, value))
+        .collect()
+}
+
+/// The keys of the profile `name`, from `profile.<name>.<key>` configuration keys
+pub fn profile(configuration: &BTreeMap<String, String>, name: &str) -> BTreeMap<String, String> {
+    let prefix = format!("profile.{}.", normalize_key(name));
+    configuration
+        .iter()
+        .filter_map(|(key, value)| Some((key.strip_prefix(&prefix)?.to_string(), value.clone())))
+        .collect()
+}
+
+fn key_problem(key: &str, value: &str) -> Option<String> {
+    if let Some(profile_key) = key.strip_prefix("profile.") {
+        // A profile's keys take the same values as the plugin's own
+        let Some((_, name)) = profile_key.split_once('.') else {
+            return Some(format!(
+                "Unknown key '{}', profile keys look like 'profile.quit.title_color'",
+                key
+            ));
+        };
+        return key_problem(name, value).map(|problem| format!("profile {}", problem));
+    }
+    if key.starts_with('_') {
+        // Reserved by zellij, e.g. `_allow_exec_host_cmd`
+        return None;
+    }
+    if let Some(id) = key.strip_prefix("string.") {
+        return (!strings::is_known(id)).then(|| format!("Unknown string '{}'", id));
+    }
+    if key.starts_with("rule_") {
+        return Rule::from_config(key, value).is_none().then(|| {
+            format!(
+                "{}: '{}' is not a rule like 'command=vim => escalate'",
+                key, value
+            )
+        });
+    }
+    let kind = match KEYS.iter().find(|(name, _)| *name == key) {
+        Some((_, kind)) => kind,
+        None if is_action_key(key.strip_suffix("_text")) => &ValueKind::Text,
+        None if is_action_key(key.strip_suffix("_confirm_key")) => &ValueKind::Keys,
+        None if is_action_key(key.strip_prefix("confirm_")) => &ValueKind::Bool,
+        None => return Some(format!("Unknown key '{}'", key)),
+    };
+    value_problem(kind, value).map(|problem| format!("{}: {}", key, problem))
+}
+
+/// Whether what's left of a `<action>_...` key names an action
+fn is_action_key(action: Option<&str>) -> bool {
+    action.is_some_and(|action| Action::parse_step(action).is_some())
+}
+
+fn value_problem(kind: &ValueKind, value: &str) -> Option<String> {
+    let valid = match kind {
+        ValueKind::Bool => parse_bool(value).is_some(),
+        ValueKind::Duration => parse_duration(value).is_some(),
+        ValueKind::Number => value.parse::<u64>().is_ok(),
+        ValueKind::Color => value.parse::<usize>().is_ok_and(|index| index <= 3),
+        ValueKind::Keys => value
+            .split(',')
+            .all(|key| KeyWithModifier::from_str(key.trim()).is_ok()),
+        ValueKind::OptionalKey => value.is_empty() || KeyWithModifier::from_str(value).is_ok(),
+        ValueKind::Actions => Action::parse(value).is_some(),
+        ValueKind::Text => true,
+        ValueKind::Choice(is_valid, _) => is_valid(value),
+    };
+    if valid {
+        return None;
+    }
+    let expected = match kind {
+        ValueKind::Bool => "true or false (or yes/no, 1/0)",
+        ValueKind::Duration => "a duration like 5s, 500ms or 2m",
+        ValueKind::Number => "a whole number",
+        ValueKind::Color => "a color index from 0 to 3",
+        ValueKind::Keys => "a comma-separated list of keys",
+        ValueKind::OptionalKey => "a key, or empty",
+        ValueKind::Actions => "an action, or a comma-separated chain of them",
+        ValueKind::Text => "text",
+        ValueKind::Choice(_, choices) => choices,
+    };
+    Some(format!("'{}' is not {}", value, expected))
+}
+
+/// Spell a configuration key in snake_case, so `confirmKey` and `confirm-key` both mean
+/// `confirm_key`
+pub fn normalize_key(key: &str) -> String {
+    let mut normalized = String::with_capacity(key.len());
+    let mut previous: Option<char> = None;
+    for c in key.chars() {
+        match c {
+            '-' => normalized.push('_'),
+            c if c.is_ascii_uppercase() => {
+                // A new word starts where lowercase turns to uppercase, `TIMEOUT` stays one word
+                if previous.is_some_and(|previous| {
+                    previous.is_ascii_lowercase() || previous.is_ascii_digit()
+                }) {
+                    normalized.push('_');
+                }
+                normalized.push(c.to_ascii_lowercase());
+            }
+            c => normalized.push(c),
+        }
+        previous = Some(c);
+    }
+    normalized
+}
+
+/// The configuration with every key spelled in snake_case
+pub fn normalize(configuration: BTreeMap<String, String>) -> BTreeMap<String, String> {
+    configuration
+        .into_iter()
+        .map(|(key, value)| (normalize_key(&key), value))
+        .collect()
+}
+
+/// Parse `true`/`false`, `yes`/`no` or `1`/`0`, in any case
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "yes" | "1" => Some(true),
+        "false" | "no" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse a duration like `500ms`, `5s`, `2m` or `1h`, where a bare number counts seconds
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(unit_start);
+    let number: u64 = number.parse().ok()?;
+    match unit.trim() {
+        "ms" => Some(Duration::from_millis(number)),
+        "" | "s" => Some(Duration::from_secs(number)),
+        "m" => Some(Duration::from_secs(number * 60)),
+        "h" => Some(Duration::from_secs(number * 3600)),
+        _ => None,
+    }
+}
+
+/// Where `config_file` is found in the plugin's `/host` mount of the folder zellij started in,
+/// `initial_cwd`, for paths relative to it or absolute ones inside it. A leading `~` stands for
+/// `$HOME`, which has to be inside that folder too; nothing outside it is reachable
+pub fn host_path(config_file: &str, initial_cwd: &Path) -> Result<PathBuf, String> {
+    let expanded;
+    let path = match config_file.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            let Some(home) = std::env::var_os("HOME") else {
+                return Err(format!(
+                    "config_file: {} starts with ~ but $HOME is unknown, give a path inside {}, \
+                     the folder zellij started in",
+                    config_file,
+                    initial_cwd.display()
+                ));
+            };
+            expanded = Path::new(&home).join(rest.trim_start_matches('/'));
+            expanded.as_path()
+        }
+        _ => Path::new(config_file),
+    };
+    if path.is_relative() {
+        return Ok(Path::new("/host").join(path));
+    }
+    match path.strip_prefix(initial_cwd) {
+        Ok(relative) => Ok(Path::new("/host").join(relative)),
+        Err(_) => Err(format!(
+            "config_file: {} is outside {}, the folder zellij started in, and only files inside \
+             it can be read",
+            path.display(),
+            initial_cwd.display()
+        )),
+    }
+}
+
+/// Read the `key "value"` lines of a configuration file, in the same form as the children of a
+/// plugin block, along with a problem for every line that isn't one. This is a line by line
+/// reader rather than a KDL parser: every setting takes one line, and multi-line values, nodes
+/// with children and `/* */` comments aren't understood
+pub fn read_file(path: &Path) -> (BTreeMap<String, String>, Vec<String>) {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => parse_file(&contents),
+        Err(error) => (
+            BTreeMap::new(),
+            vec![format!(
+                "config_file: could not read {}: {}",
+                path.display(),
+                error
+            )],
+        ),
+    }
+}
+
+fn parse_file(contents: &str) -> (BTreeMap<String, String>, Vec<String>) {
+    let mut configuration = BTreeMap::new();
+    let mut problems = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        match parse_line(line) {
+            Some((key, value)) => {
+                configuration.insert(normalize_key(key), value);
+            }
+            None => problems.push(format!(
+                "config_file: line {} is not a key \"value\" line, the file is read line by line \
+                 rather than as KDL: {}",
+                index + 1,
+                line
+            )),
+        }
+    }
+    (configuration, problems)
+}
+
+/// A keybind running the plugin with the given configuration on `key`, ready to paste into the
+/// zellij config
+pub fn keybind_snippet(configuration: &BTreeMap<String, String>, key: &str) -> String {
+    let mut snippet = String::from("keybinds {\n    shared_except \"locked\" {\n");
+    snippet.push_str(&format!("        bind {} {{\n", quote(key)));
+    snippet.push_str("            LaunchOrFocusPlugin \"zj-quit\" {\n");
+    snippet.push_str("                floating true\n");
+    for (key, value) in configuration {
+        if key.starts_with('_') {
+            // Reserved by zellij, not ours to hand out
+            continue;
+        }
+        snippet.push_str(&format!("                {} {}\n", key, quote(value)));
+    }
+    snippet.push_str("            }\n        }\n    }\n}\n");
+    snippet
+}
+
+/// A KDL string holding `value`, the reverse of `parse_line`'s unescaping
+fn quote(value: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Split `key "value"` or `key value` in two, unescaping a quoted value
+fn parse_line(line: &str) -> Option<(&str, String)> {
+    let (key, value) = line.split_once(char::is_whitespace)?;
+    let value = value.trim();
+    let Some(quoted) = value.strip_prefix('"') else {
+        return Some((key, value.to_string()));
+    };
+    let mut unescaped = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return chars.as_str().trim().is_empty().then_some((key, unescaped)),
+            '\\' => match chars.next()? {
+                'n' => unescaped.push('\n'),
+                't' => unescaped.push('\t'),
+                escaped => unescaped.push(escaped),
+            },
+            c => unescaped.push(c),
+        }
+    }
+    // The closing quote is missing
+    None
+}
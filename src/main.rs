@@ -1,232 +1,4384 @@
 use zellij_tile::prelude::*;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
+use std::ops::Range;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+mod config;
+mod processes;
+mod rules;
+mod strings;
+
+use processes::ProcessWorker;
+use rules::{Rule, RuleOutcome, RuleTarget};
+use serde::Deserialize;
+use strings::Strings;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// The action to perform after confirmation
-#[derive(Debug, Clone, PartialEq)]
-enum Action {
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) enum Action {
     /// Quit the entire zellij session (original behavior)
+    #[default]
     QuitSession,
+    /// Detach from the session, leaving it running in the background
+    DetachSession,
+    /// Kill every running session, including the current one
+    KillAllSessions,
+    /// Kill another session, named by the `session_name` configuration
+    KillSession,
+    /// Kill the current session and delete it, so it can't be resurrected
+    KillAndDeleteSession,
     /// Close the focused pane
     ClosePane,
+    /// Close every pane in the focused tab except the focused one
+    CloseOtherPanes,
+    /// Re-run the command of the focused command pane
+    RestartPane,
+    /// Close every floating pane in the focused tab
+    CloseFloatingPanes,
+    /// Close every command pane in the session whose command exited
+    CloseExitedPanes,
+    /// Send an interrupt (^C) to every terminal pane, in the session or in the focused tab
+    InterruptAll,
     /// Close the focused tab
     CloseTab,
+    /// Close every tab except the focused one
+    CloseOtherTabs,
+    /// Close every tab to the right of the focused one
+    CloseTabsToRight,
+    /// Save the session layout to the `layout_file` in the plugin's host folder
+    DumpLayout,
+    /// Close the panes picked from a list of every pane in the session with an idle shell
+    CloseIdlePanes,
+    /// Several actions executed in order after a single confirmation
+    Chain(Vec<Action>),
+    /// Nothing but the question, asked through a `confirm` pipe message by a script or plugin
+    Confirm,
 }
 
-impl Default for Action {
-    fn default() -> Self {
-        Action::QuitSession
+impl Action {
+    /// Parse an action, or a comma-separated chain of actions (e.g. "dump_layout,close_tab")
+    pub(crate) fn from_config(s: &str) -> Self {
+        let mut steps: Vec<Action> = s
+            .split(',')
+            .map(str::trim)
+            .filter(|step| !step.is_empty())
+            .map(Action::from_step_config)
+            .collect();
+        match steps.len() {
+            0 => Action::default(),
+            1 => steps.remove(0),
+            _ => Action::Chain(steps),
+        }
     }
-}
 
-impl Action {
-    fn from_config(s: &str) -> Self {
-        match s.to_lowercase().as_str() {
+    /// Parse an action or chain like `from_config`, or `None` if any step isn't an action
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        let mut steps = s
+            .split(',')
+            .map(str::trim)
+            .map(Action::parse_step)
+            .collect::<Option<Vec<_>>>()?;
+        match steps.len() {
+            1 => Some(steps.remove(0)),
+            _ => Some(Action::Chain(steps)),
+        }
+    }
+
+    fn from_step_config(s: &str) -> Self {
+        // Anything unrecognized falls back to quitting, the original behavior
+        Action::parse_step(s).unwrap_or_default()
+    }
+
+    /// Parse a single action name, or `None` if it isn't one
+    pub(crate) fn parse_step(s: &str) -> Option<Self> {
+        let action = match s.to_lowercase().as_str() {
+            "quit" | "quit_session" | "session" => Action::QuitSession,
             "close_pane" | "closepane" | "pane" => Action::ClosePane,
+            "close_other_panes" | "closeotherpanes" | "other_panes" => Action::CloseOtherPanes,
+            "restart_pane" | "restartpane" | "rerun" => Action::RestartPane,
+            "close_floating_panes" | "closefloatingpanes" | "floating_panes" => {
+                Action::CloseFloatingPanes
+            }
+            "close_exited_panes" | "closeexitedpanes" | "exited_panes" => Action::CloseExitedPanes,
+            "interrupt_all" | "interruptall" | "stop_all" => Action::InterruptAll,
             "close_tab" | "closetab" | "tab" => Action::CloseTab,
-            "quit" | "quit_session" | "session" | _ => Action::QuitSession,
+            "close_other_tabs" | "closeothertabs" | "other_tabs" => Action::CloseOtherTabs,
+            "close_tabs_to_right" | "closetabstoright" | "tabs_to_right" => {
+                Action::CloseTabsToRight
+            }
+            "detach" | "detach_session" | "detachsession" => Action::DetachSession,
+            "kill_all_sessions" | "killallsessions" | "all_sessions" => Action::KillAllSessions,
+            "kill_session" | "killsession" => Action::KillSession,
+            "kill_and_delete_session" | "killanddeletesession" | "delete_session" => {
+                Action::KillAndDeleteSession
+            }
+            "dump_layout" | "dumplayout" | "layout" => Action::DumpLayout,
+            "close_idle_panes" | "closeidlepanes" | "idle_panes" | "cleanup" => {
+                Action::CloseIdlePanes
+            }
+            _ => return None,
+        };
+        Some(action)
+    }
+
+    /// The action's name in `string.<kind>.<name>` keys, e.g. `string.title.close_pane`
+    fn string_name(&self) -> &'static str {
+        match self {
+            Action::QuitSession => "quit_session",
+            Action::DetachSession => "detach_session",
+            Action::KillAllSessions => "kill_all_sessions",
+            Action::KillSession => "kill_session",
+            Action::KillAndDeleteSession => "kill_and_delete_session",
+            Action::ClosePane => "close_pane",
+            Action::CloseOtherPanes => "close_other_panes",
+            Action::RestartPane => "restart_pane",
+            Action::CloseFloatingPanes => "close_floating_panes",
+            Action::CloseExitedPanes => "close_exited_panes",
+            Action::InterruptAll => "interrupt_all",
+            Action::CloseTab => "close_tab",
+            Action::CloseOtherTabs => "close_other_tabs",
+            Action::CloseTabsToRight => "close_tabs_to_right",
+            Action::DumpLayout => "dump_layout",
+            Action::CloseIdlePanes => "close_idle_panes",
+            Action::Chain(_) => "chain",
+            Action::Confirm => "confirm",
+        }
+    }
+
+    fn confirmation_text(&self, strings: &Strings) -> String {
+        strings
+            .get(&format!("text.{}", self.string_name()))
+            .to_string()
+    }
+
+    fn action_name(&self, strings: &Strings) -> String {
+        strings
+            .get(&format!("title.{}", self.string_name()))
+            .to_string()
+    }
+
+    /// The Nerd Font glyph put before the title with `icons "true"`
+    fn icon(&self) -> &'static str {
+        match self {
+            Action::QuitSession
+            | Action::DetachSession
+            | Action::KillAllSessions
+            | Action::KillSession
+            | Action::KillAndDeleteSession => "\u{f011}", // nf-fa-power_off
+            Action::ClosePane
+            | Action::CloseOtherPanes
+            | Action::RestartPane
+            | Action::CloseFloatingPanes
+            | Action::CloseExitedPanes
+            | Action::InterruptAll
+            | Action::CloseIdlePanes => "\u{f489}", // nf-oct-terminal
+            Action::CloseTab | Action::CloseOtherTabs | Action::CloseTabsToRight => "\u{f04e9}", // nf-md-tab
+            Action::DumpLayout => "\u{f0c7}", // nf-fa-floppy_o
+            // The last step is usually the one that takes the most down
+            Action::Chain(steps) => steps.last().map_or("\u{f0ae}", Action::icon), // nf-fa-tasks
+            Action::Confirm => "\u{f059}", // nf-fa-question_circle
+        }
+    }
+
+    /// The dialog title, spelling out every step of a chain
+    fn title(&self, strings: &Strings) -> String {
+        match self {
+            Action::Chain(steps) => steps
+                .iter()
+                .map(|step| step.action_name(strings))
+                .collect::<Vec<_>>()
+                .join(" > "),
+            action => action.action_name(strings),
         }
     }
 
-    fn confirmation_text(&self) -> &'static str {
+    /// The individual actions to execute, in order
+    fn steps(&self) -> &[Action] {
         match self {
-            Action::QuitSession => "Are you sure you want to quit this session?",
-            Action::ClosePane => "Are you sure you want to close this pane?",
-            Action::CloseTab => "Are you sure you want to close this tab?",
+            Action::Chain(steps) => steps,
+            action => std::slice::from_ref(action),
+        }
+    }
+
+    /// Whether this action operates on the focused pane, which we need to capture on open
+    fn targets_pane(&self) -> bool {
+        self.steps().iter().any(|step| {
+            matches!(
+                step,
+                Action::ClosePane | Action::CloseOtherPanes | Action::RestartPane
+            )
+        })
+    }
+}
+
+/// A non-destructive (or differently scoped) choice offered next to confirming the action
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Alternative {
+    /// Detach instead of quitting the session
+    Detach,
+    /// Move the target pane to a floating pane instead of closing it
+    Float,
+    /// Close every pane in the target pane's stack instead of just the target
+    CloseStack,
+    /// Send an interrupt (^C) to the target pane instead of closing it
+    Interrupt,
+    /// Disconnect the other attached clients instead of quitting the session
+    DisconnectOthers,
+}
+
+impl Alternative {
+    fn description(&self) -> &'static str {
+        match self {
+            Alternative::Detach => "detach",
+            Alternative::Float => "float",
+            Alternative::CloseStack => "close stack",
+            Alternative::Interrupt => "interrupt",
+            Alternative::DisconnectOthers => "disconnect others",
+        }
+    }
+
+    /// The id of the alternative's help hint in the string table
+    fn hint_id(&self) -> &'static str {
+        match self {
+            Alternative::Detach => "hint.detach",
+            Alternative::Float => "hint.float",
+            Alternative::CloseStack => "hint.close_stack",
+            Alternative::Interrupt => "hint.interrupt",
+            Alternative::DisconnectOthers => "hint.disconnect_others",
+        }
+    }
+}
+
+/// One of the buttons below the dialog text, selected with Left/Right or Tab and pressed with Enter
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum Button {
+    Confirm,
+    /// Selected when the dialog opens unless `default_button` says otherwise, following the
+    /// [y/N] convention
+    #[default]
+    Cancel,
+}
+
+impl Button {
+    pub(crate) fn from_config(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "confirm" => Some(Button::Confirm),
+            "cancel" => Some(Button::Cancel),
+            _ => None,
+        }
+    }
+
+    fn label(&self, strings: &Strings) -> String {
+        let label = match self {
+            Button::Confirm => strings.get("button.confirm"),
+            Button::Cancel => strings.get("button.cancel"),
+        };
+        format!("[ {} ]", label)
+    }
+
+    fn other(&self) -> Self {
+        match self {
+            Button::Confirm => Button::Cancel,
+            Button::Cancel => Button::Confirm,
+        }
+    }
+}
+
+/// Where to move focus after the ClosePane action closed its target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FocusAfterClose {
+    /// Move focus in a direction, like the `MoveFocus` keybind action
+    Direction(Direction),
+    /// Focus the previous pane, like the `FocusPreviousPane` keybind action
+    Previous,
+    /// Focus the next pane, like the `FocusNextPane` keybind action
+    Next,
+}
+
+impl FocusAfterClose {
+    pub(crate) fn from_config(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "left" => Some(FocusAfterClose::Direction(Direction::Left)),
+            "right" => Some(FocusAfterClose::Direction(Direction::Right)),
+            "up" => Some(FocusAfterClose::Direction(Direction::Up)),
+            "down" => Some(FocusAfterClose::Direction(Direction::Down)),
+            "previous" | "prev" => Some(FocusAfterClose::Previous),
+            "next" => Some(FocusAfterClose::Next),
+            _ => None,
         }
     }
 
-    fn action_name(&self) -> &'static str {
+    fn apply(&self) {
         match self {
-            Action::QuitSession => "Quit Session",
-            Action::ClosePane => "Close Pane",
-            Action::CloseTab => "Close Tab",
+            FocusAfterClose::Direction(direction) => move_focus(*direction),
+            FocusAfterClose::Previous => focus_previous_pane(),
+            FocusAfterClose::Next => focus_next_pane(),
+        }
+    }
+}
+
+/// Which tab to focus after the CloseTab action closed its target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FocusTabAfterClose {
+    Previous,
+    Next,
+    First,
+    Last,
+}
+
+impl FocusTabAfterClose {
+    pub(crate) fn from_config(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "previous" | "prev" | "left" => Some(FocusTabAfterClose::Previous),
+            "next" | "right" => Some(FocusTabAfterClose::Next),
+            "first" => Some(FocusTabAfterClose::First),
+            "last" => Some(FocusTabAfterClose::Last),
+            _ => None,
         }
     }
+
+    /// The 0-indexed position to focus once the tab at `closed_position` is gone,
+    /// out of `tab_count` tabs before closing
+    fn position_after_close(&self, closed_position: usize, tab_count: usize) -> Option<usize> {
+        let remaining = tab_count
+            .checked_sub(1)
+            .filter(|remaining| *remaining > 0)?;
+        let position = match self {
+            FocusTabAfterClose::Previous => closed_position.saturating_sub(1),
+            // The tab to the right slides into the closed tab's position
+            FocusTabAfterClose::Next => closed_position,
+            FocusTabAfterClose::First => 0,
+            FocusTabAfterClose::Last => remaining - 1,
+        };
+        Some(position.min(remaining - 1))
+    }
 }
 
+#[derive(Default)]
 struct State {
     action: Action,
-    /// The pane that was focused before the plugin opened (for ClosePane action)
+    /// The id of this plugin instance, so we never close our own pane
+    plugin_id: u32,
+    /// The pane that was focused before the plugin opened (for pane actions)
     target_pane_id: Option<PaneId>,
     /// The tab index that was focused before the plugin opened (for CloseTab action)
     target_tab_index: Option<usize>,
+    /// The panes of the target tab when it was captured, which tell it apart from a tab taking
+    /// its position later
+    target_tab_pane_ids: Vec<PaneId>,
     /// Whether we've received pane info yet
     pane_info_received: bool,
+    /// Whether the open dialog was answered, so updates arriving after that leave it alone
+    answered: bool,
+    /// Whether the open dialog moves on to the pane and tab focused in the meantime
+    follow_focus: bool,
+    /// The focused tab and pane as of the latest update, for `follow_focus` to notice them change
+    seen_focus: Option<(usize, Option<PaneId>)>,
+    /// The latest tab list reported by zellij (for CloseOtherTabs action)
+    tabs: Vec<TabInfo>,
+    /// The latest pane manifest reported by zellij (for CloseOtherPanes action)
+    panes: PaneManifest,
+    /// Whether CloseOtherPanes should also close plugin panes
+    close_plugin_panes: bool,
+    /// The latest list of running sessions reported by zellij (for KillAllSessions/KillSession actions)
+    sessions: Vec<SessionInfo>,
+    /// The session to kill (for KillSession action)
+    session_name: Option<String>,
+    /// Where to save the session layout, relative to the host folder (for DumpLayout action)
+    layout_file: String,
+    /// Steps of the confirmed action that still have to run
+    pending_steps: VecDeque<Action>,
+    /// Whether dismissing the error screen quits the session, after deleting it failed
+    quit_after_error: bool,
+    /// Whether we're waiting for zellij to send the session layout before continuing
+    awaiting_layout: bool,
+    /// Whether InterruptAll covers only the focused tab rather than the whole session
+    interrupt_tab_only: bool,
+    /// Panes InterruptAll still has to interrupt, one per timer tick so progress can render
+    interrupt_queue: VecDeque<PaneId>,
+    /// How many panes InterruptAll started with, for the progress line
+    interrupt_total: usize,
+    /// When the next queued pane is due for its interrupt, telling InterruptAll's timer apart
+    /// from countdown ticks set before it started
+    interrupt_due: Option<Instant>,
+    /// Why the last execution failed, shown instead of the confirmation dialog
+    error: Option<String>,
+    /// Keys confirming the dialog, `y` by default following the [y/N] convention (matching `Y`
+    /// too)
+    confirm_keys: Vec<KeyWithModifier>,
+    /// Confirm keys replacing `confirm_keys` for some actions, from `<action>_confirm_key`
+    /// configuration keys
+    action_confirm_keys: Vec<(Action, Vec<KeyWithModifier>)>,
+    /// Keys dismissing the dialog, `Esc` and `n` by default (matching `N` too)
+    cancel_keys: Vec<KeyWithModifier>,
+    /// Whether every key without a use dismisses the dialog, rather than flashing a hint
+    any_key_cancels: bool,
+    /// Whether h and l move between the buttons, like j and k already move in the lists
+    vim_keys: bool,
+    /// Key that detaches instead of quitting in the QuitSession dialog
+    detach_key: Option<KeyWithModifier>,
+    /// Key that moves the target pane to a floating pane instead of closing it in the ClosePane dialog
+    float_key: Option<KeyWithModifier>,
+    /// Key that closes the target pane's whole stack in the ClosePane dialog
+    stack_key: Option<KeyWithModifier>,
+    /// Key that interrupts the target pane's process instead of closing it in the ClosePane dialog
+    interrupt_key: Option<KeyWithModifier>,
+    /// Key that disconnects the other clients instead of quitting in the QuitSession dialog
+    disconnect_key: Option<KeyWithModifier>,
+    /// Where focus goes once the ClosePane action closed its target, zellij decides if unset
+    focus_after_close: Option<FocusAfterClose>,
+    /// Which tab gets focus once the CloseTab action closed its target, zellij decides if unset
+    focus_tab_after_close: Option<FocusTabAfterClose>,
+    /// Whether closing the last tab goes through the QuitSession dialog instead
+    last_tab_quits: bool,
+    /// The command in the foreground of each terminal pane, its shell when nothing else runs, as
+    /// last found in `/proc`
+    running_commands: BTreeMap<PaneId, String>,
+    /// How many processes run below each terminal pane's shell, background jobs included, as
+    /// last found in `/proc`
+    child_process_counts: BTreeMap<PaneId, usize>,
+    /// Whether pane actions run without confirmation when the target is an idle shell
+    skip_when_idle: bool,
+    /// Commands that are fine to lose, pane actions run without confirmation when the target runs one
+    safe_commands: Vec<String>,
+    /// Whether we're waiting for the running commands to decide if the dialog can be skipped
+    skip_check_pending: bool,
+    /// When to stop waiting for the running commands and show the dialog anyway
+    skip_check_deadline: Option<Instant>,
+    /// Whether quitting goes without reading the session's state, and the permission for it
+    minimal_permissions: bool,
+    /// Whether the help line below the dialog is shown, besides the prompts standing in for it
+    show_help: bool,
+    /// The help line replacing the key hints, with `{confirm_key}` and `{cancel_key}` filled in
+    help_text: Option<String>,
+    /// Whether ClosePane runs without confirmation when the target command pane already exited
+    skip_when_exited: bool,
+    /// Whether ClosePane runs without confirmation when the target pane is held, waiting to be
+    /// re-run or closed
+    skip_when_held: bool,
+    /// Commands that require an escalated (double press) confirmation when the target runs them
+    danger_commands: Vec<String>,
+    /// Whether the confirm key was already pressed once in an escalated dialog
+    confirm_armed: bool,
+    /// The alternative whose key was already pressed once, in a dialog where it takes two
+    /// presses like the confirm key
+    armed_alternative: Option<Alternative>,
+    /// Whether every dialog takes the confirm key twice, like an escalated one
+    double_confirm: bool,
+    /// When a `double_confirm` first press no longer counts
+    armed_deadline: Option<Instant>,
+    /// Quitting a session with more tabs than this requires an escalated confirmation
+    escalate_tabs: Option<usize>,
+    /// Quitting a session with more panes than this requires an escalated confirmation
+    escalate_panes: Option<usize>,
+    /// Actions configured with `confirm_<action> "false"`, which run without the dialog
+    unconfirmed_actions: Vec<Action>,
+    /// Confirmation texts replacing the actions' own, from `<action>_text` configuration keys
+    confirmation_texts: Vec<(Action, String)>,
+    /// Tab name patterns whose panes and tabs close without confirmation
+    skip_tabs: Vec<String>,
+    /// Configured `rule_<n>` entries, the first match decides whether to skip or escalate
+    rules: Vec<Rule>,
+    /// Whether closing a pane running an editor requires typing the confirmation word
+    editor_typed_confirm: bool,
+    /// The word every dialog requires typed out, placeholders and all, if configured
+    typed_confirm_word: Option<String>,
+    /// What was typed so far in a typed confirmation
+    typed_input: String,
+    /// Whether pane dialogs list the processes running in the target pane
+    show_processes: bool,
+    /// The target pane's process tree, one indented process per line
+    target_processes: Vec<String>,
+    /// Pane actions require an escalated confirmation when the foreground command has been
+    /// running for longer than this
+    long_running_minutes: Option<u64>,
+    /// Seconds the target pane's foreground command has been running, and its command line
+    foreground_elapsed: Option<(u64, String)>,
+    /// Whether dialogs warn about uncommitted changes in the worktrees of the affected panes
+    check_git: bool,
+    /// Whether the title and target line start with Nerd Font glyphs
+    icons: bool,
+    /// Whether the dialog sticks to plain ASCII, leaving out icons and box-drawing characters
+    ascii_only: bool,
+    /// The characters the dialog's border is drawn with
+    border: BorderStyle,
+    /// The most columns the lines of the dialog take, however wide the pane is, if configured
+    max_width: Option<usize>,
+    /// How long an unanswered dialog stays open before it answers itself, if configured
+    timeout: Option<Duration>,
+    /// Whether the user denied the permissions the plugin asked for
+    permissions_denied: bool,
+    /// How long the result screen stays up after closing panes or tabs, if it is shown at all
+    result_duration: Option<Duration>,
+    /// What the confirmed action will have done, shown once its steps are through
+    pending_result: Option<String>,
+    /// What the confirmed action did, shown instead of the dialog until `result_deadline`
+    result: Option<String>,
+    result_deadline: Option<Instant>,
+    /// How long a confirmed QuitSession waits for a key that takes it back, if configured
+    abort_window: Option<Duration>,
+    /// When the confirmed QuitSession goes through, while it can still be aborted
+    quit_deadline: Option<Instant>,
+    /// Whether the countdown running out confirms the dialog rather than cancelling it
+    timeout_confirms: bool,
+    /// When the running countdown runs out
+    countdown_deadline: Option<Instant>,
+    /// Whether a countdown timer is already set, so ticks don't pile up
+    countdown_tick_pending: bool,
+    /// When the hint flashed for an unrecognized key goes away
+    key_hint_deadline: Option<Instant>,
+    /// Emphasis color indices (0-3) of the title, the "Warning:" and "Target:" prefixes and the
+    /// keys in the help line
+    title_color: usize,
+    warning_color: usize,
+    target_color: usize,
+    key_hint_color: usize,
+    /// Worktrees with uncommitted changes that affected panes are in
+    dirty_repos: Vec<String>,
+    /// When each running command pane was first seen running, by terminal pane id
+    command_started: BTreeMap<u32, Instant>,
+    /// How long the last run of each exited command pane took, if it was seen starting
+    command_runtimes: BTreeMap<u32, Duration>,
+    /// Panes found with an idle shell for CloseIdlePanes, and whether each is selected
+    idle_panes: Vec<(PaneId, bool)>,
+    /// Index of the highlighted entry in `idle_panes`
+    idle_cursor: usize,
+    /// How far the CloseTab dialog's pane list is scrolled down
+    tab_pane_scroll: usize,
+    /// How many entries of the CloseTab dialog's pane list fit, as of the last render
+    tab_pane_rows: usize,
+    /// Whether we're still waiting for the idle pane scan
+    idle_scan_pending: bool,
+    /// Dialog title replacing the action's, from a JSON pipe payload
+    custom_title: Option<String>,
+    /// Confirmation text replacing the action's, from a JSON pipe payload
+    custom_message: Option<String>,
+    /// Who opened the dialog through a pipe and waits for the user's decision
+    requester: Option<Requester>,
+    /// Whether the plugin pane is currently shown
+    visible: bool,
+    /// What is wrong with the configuration, listed until a key dismisses it
+    config_problems: Vec<String>,
+    /// The dialog's own strings, with any `string.<id>` overrides
+    strings: Strings,
+    /// The configuration the plugin was loaded with, plus any `reload_config` changes
+    block_configuration: BTreeMap<String, String>,
+    /// `block_configuration` merged over the `config_file`'s, as applied
+    configuration: BTreeMap<String, String>,
+    /// Where the `config_file` is read from, if one is configured
+    config_file_path: Option<std::path::PathBuf>,
+    /// Whether the open dialog runs with pipe arguments merged over `configuration`
+    configuration_overridden: bool,
+    /// The only pipe channel this instance listens on, if configured
+    pipe_name: Option<String>,
+    /// Name of the message broadcast to every plugin once the user decided, if configured
+    broadcast_message: Option<String>,
+    /// Payload of the broadcast message, `{decision}` and `{action}` are filled in
+    broadcast_payload: String,
+    /// Whether another plugin sent a `confirm` message, which is answered by messaging it back
+    answers_plugins: bool,
+    /// The session's theme colors, once the first `ModeUpdate` arrived
+    styling: Option<Styling>,
+    /// The name of the session the plugin runs in, as reported by `ModeUpdate`
+    current_session_name: Option<String>,
+    /// The button Enter presses
+    focused_button: Button,
+    /// The button focused when the dialog opens
+    default_button: Button,
+    /// The button under the mouse pointer, if any
+    hovered_button: Option<Button>,
+    /// Where each button was last rendered, as its row and columns, for mouse clicks
+    button_positions: Vec<(Button, usize, Range<usize>)>,
+}
+
+/// Someone waiting for the user's decision on a dialog they opened through a pipe
+enum Requester {
+    /// A `zellij pipe` process, identified by its pipe id, kept blocked until the user decides
+    CliPipe(String),
+    /// Another plugin that sent a `confirm` message, with the `request_id` it passed, if any
+    Plugin {
+        plugin_id: u32,
+        request_id: Option<String>,
+    },
+}
+
+/// A JSON pipe payload, e.g. `{"action":"close_tab","title":"Ship it?"}`
+#[derive(Deserialize)]
+struct PipePayload {
+    action: Option<String>,
+    title: Option<String>,
+    message: Option<String>,
+    id: Option<String>,
 }
 
-impl Default for State {
-    fn default() -> Self {
-        Self {
-            action: Action::default(),
+/// What a pipe message asks the dialog to show
+struct DialogRequest {
+    action: Action,
+    /// Replaces the action's title
+    title: Option<String>,
+    /// Replaces the action's confirmation text
+    message: Option<String>,
+    /// The pane to act on instead of the focused one
+    target_pane_id: Option<PaneId>,
+}
+
+impl DialogRequest {
+    fn new(action: Action) -> Self {
+        DialogRequest {
+            action,
+            title: None,
+            message: None,
             target_pane_id: None,
-            target_tab_index: None,
-            pane_info_received: false,
         }
     }
 }
 
-/// Check if a key is 'y' or 'Y' (confirm), following [y/N] CLI convention
-fn is_confirm(key: &KeyWithModifier) -> bool {
-    matches!(key.bare_key, BareKey::Char('y') | BareKey::Char('Y'))
+/// Parse a pane id like `terminal:42`, `plugin:7` or just `42` for a terminal pane
+fn parse_pane_id(s: &str) -> Option<PaneId> {
+    PaneId::from_str(&s.replace(':', "_")).ok()
+}
+
+/// Build the `PaneId` that refers to the given pane
+fn pane_id_of(pane_info: &PaneInfo) -> PaneId {
+    if pane_info.is_plugin {
+        PaneId::Plugin(pane_info.id)
+    } else {
+        PaneId::Terminal(pane_info.id)
+    }
+}
+
+/// The bare number of a pane id, as shown in pane titles
+fn pane_id_number(pane_id: &PaneId) -> u32 {
+    match pane_id {
+        PaneId::Terminal(id) | PaneId::Plugin(id) => *id,
+    }
+}
+
+/// The executable name of a command line, without its path or login-shell dash
+pub(crate) fn command_name(command: &str) -> &str {
+    let executable = command.split_whitespace().next().unwrap_or_default();
+    let name = executable.rsplit('/').next().unwrap_or(executable);
+    name.trim_start_matches('-')
+}
+
+/// Whether a command line is an interactive shell
+fn is_shell(command: &str) -> bool {
+    matches!(
+        command_name(command),
+        "sh" | "bash"
+            | "zsh"
+            | "fish"
+            | "dash"
+            | "ksh"
+            | "tcsh"
+            | "csh"
+            | "nu"
+            | "elvish"
+            | "xonsh"
+            | "pwsh"
+    )
+}
+
+/// Split a comma-separated configuration value into its non-empty items
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The ANSI escape selecting `color` as the foreground color
+fn ansi_foreground(color: PaletteColor) -> String {
+    match color {
+        PaletteColor::Rgb((r, g, b)) => format!("\u{1b}[38;2;{};{};{}m", r, g, b),
+        PaletteColor::EightBit(index) => format!("\u{1b}[38;5;{}m", index),
+    }
+}
+
+/// Color every range of a text in the given emphasis color
+fn color_ranges(text: Text, color: usize, ranges: Vec<Range<usize>>) -> Text {
+    ranges
+        .into_iter()
+        .fold(text, |text, range| text.color_range(color, range))
+}
+
+/// Print text in bold at the given coordinates, for dangerous or escalated dialogs, in the
+/// theme's error color or red until the theme is known
+///
+/// `Text` components can only use the theme's emphasis colors, so this uses raw ANSI
+fn print_danger_text(text: &str, x: usize, y: usize, styling: Option<&Styling>) {
+    print!(
+        "\u{1b}[{};{}H\u{1b}[1m{}{}\u{1b}[0m",
+        y + 1,
+        x + 1,
+        danger_color(styling),
+        text
+    );
+}
+
+/// Print text in inverse bold at the given coordinates, so it stands out as a block of the
+/// danger color
+fn print_danger_block(text: &str, x: usize, y: usize, styling: Option<&Styling>) {
+    print!(
+        "\u{1b}[{};{}H\u{1b}[1;7m{}{}\u{1b}[0m",
+        y + 1,
+        x + 1,
+        danger_color(styling),
+        text
+    );
+}
+
+/// The ANSI escape selecting the theme's error color, or red until the theme is known
+fn danger_color(styling: Option<&Styling>) -> String {
+    match styling {
+        Some(styling) => ansi_foreground(styling.exit_code_error.base),
+        None => "\u{1b}[31m".to_string(),
+    }
+}
+
+/// The number of columns `text` takes in a terminal, wide characters counting double
+fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+/// The longest start of `text` that fits in `width` columns
+fn truncate_to_width(text: &str, width: usize) -> String {
+    let mut used = 0;
+    text.chars()
+        .take_while(|c| {
+            used += c.width().unwrap_or(0);
+            used <= width
+        })
+        .collect()
+}
+
+/// Break a line of text into lines of at most `width` columns, between words where possible
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word = word.to_string();
+        if !line.is_empty() && display_width(&line) + 1 + display_width(&word) > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        // Words longer than a whole line get split wherever the line ends
+        while display_width(&word) > width {
+            let mut head = truncate_to_width(&word, width);
+            if head.is_empty() {
+                // A character wider than the line still has to go somewhere
+                head = word.chars().take(1).collect();
+            }
+            word = word[head.len()..].to_string();
+            lines.push(head);
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Parse one of the emphasis color indices `Text` supports from configuration, falling back to
+/// `default` when unset or out of range
+fn parse_color_index(configuration: &BTreeMap<String, String>, key: &str, default: usize) -> usize {
+    configuration
+        .get(key)
+        .and_then(|value| value.parse().ok())
+        .filter(|index| *index <= 3)
+        .unwrap_or(default)
+}
+
+/// Parse a boolean like `config::parse_bool` from configuration, falling back to `default`
+fn parse_bool(configuration: &BTreeMap<String, String>, name: &str, default: bool) -> bool {
+    configuration
+        .get(name)
+        .and_then(|value| config::parse_bool(value))
+        .unwrap_or(default)
+}
+
+/// Parse a duration like `config::parse_duration` from configuration, where zero turns off what
+/// it times
+fn parse_duration(configuration: &BTreeMap<String, String>, name: &str) -> Option<Duration> {
+    configuration
+        .get(name)
+        .and_then(|value| config::parse_duration(value))
+        .filter(|duration| !duration.is_zero())
+}
+
+/// Parse an optional alternative key from configuration, where an empty value disables it
+fn parse_alternative_key(
+    configuration: &BTreeMap<String, String>,
+    name: &str,
+    default: char,
+) -> Option<KeyWithModifier> {
+    match configuration.get(name) {
+        Some(key) if key.is_empty() => None,
+        Some(key) => KeyWithModifier::from_str(key).ok(),
+        None => Some(KeyWithModifier::new(BareKey::Char(default))),
+    }
+}
+
+/// Parse a comma-separated list of keys, e.g. `"Enter,y"`, skipping the ones that don't parse
+fn parse_key_list(value: &str) -> Vec<KeyWithModifier> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter_map(|key| KeyWithModifier::from_str(key).ok())
+        .collect()
+}
+
+/// Parse a list of keys like `parse_key_list` from configuration, falling back to `defaults`
+/// when none of them parse
+fn parse_keys(
+    configuration: &BTreeMap<String, String>,
+    name: &str,
+    defaults: &[BareKey],
+) -> Vec<KeyWithModifier> {
+    let keys = configuration
+        .get(name)
+        .map(|keys| parse_key_list(keys))
+        .unwrap_or_default();
+    if keys.is_empty() {
+        defaults
+            .iter()
+            .map(|default| KeyWithModifier::new(*default))
+            .collect()
+    } else {
+        keys
+    }
+}
+
+/// Whether `key` is one of `keys`, where letters match in either case, so the default `y` takes
+/// `Y` too
+fn contains_key(keys: &[KeyWithModifier], key: &KeyWithModifier) -> bool {
+    let fold_case = |key: &KeyWithModifier| match key.bare_key {
+        BareKey::Char(c) => {
+            let mut folded = key.clone();
+            folded.bare_key = BareKey::Char(c.to_ascii_lowercase());
+            folded.key_modifiers.remove(&KeyModifier::Shift);
+            folded
+        }
+        _ => key.clone(),
+    };
+    keys.iter()
+        .any(|configured| fold_case(configured) == fold_case(key))
+}
+
+/// A list of keys as shown in the help line, separated by slashes
+fn key_label(keys: &[KeyWithModifier]) -> String {
+    keys.iter()
+        .map(KeyWithModifier::to_string)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Format seconds as e.g. `1h 5m`, `12m 3s` or `40s`
+fn format_duration(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Whether a command line is a text editor that may hold unsaved changes
+fn is_editor(command: &str) -> bool {
+    matches!(
+        command_name(command),
+        "vi" | "vim" | "nvim" | "emacs" | "hx" | "helix"
+    )
+}
+
+/// Whether a command line holds a connection to a remote host
+fn is_remote_connection(command: &str) -> bool {
+    matches!(command_name(command), "ssh" | "mosh" | "mosh-client")
+}
+
+/// Pipe arguments that describe the dialog itself, every other argument overrides a
+/// configuration key for that invocation
+const RESERVED_PIPE_ARGS: &[&str] = &["action", "id", "title", "channel", "request_id", "profile"];
+
+/// The key bound by the snippet `print-keybinds` prints, unless the pipe passes a `key`
+const DEFAULT_KEYBIND: &str = "Ctrl q";
+
+/// The `run_command` context key of `zellij delete-session`
+const DELETE_SESSION_CONTEXT: &str = "delete_session";
+
+/// The word a typed confirmation expects before Enter is accepted
+const CONFIRMATION_WORD: &str = "yes";
+
+/// The Nerd Font glyph put before the target line with `icons "true"`, nf-fa-bullseye
+const TARGET_ICON: &str = "\u{f140}";
+
+/// Columns a ribbon takes besides its text, for its separators and padding
+const RIBBON_PADDING_COLS: usize = 4;
+
+/// Panes with fewer rows than this get the dialog as a single line
+const MIN_DIALOG_ROWS: usize = 5;
+
+/// How long the dialog stays blank waiting for the panes' commands to decide whether it's needed
+const SKIP_CHECK_SECONDS: u64 = 1;
+
+/// How long the hint flashed for an unrecognized key stays up
+const KEY_HINT_SECONDS: u64 = 1;
+
+/// How long `double_confirm` waits for the second press before starting over
+const DOUBLE_CONFIRM_SECONDS: u64 = 2;
+
+/// Render the error screen shown when an action could not be executed
+fn render_error(error: &str, strings: &Strings, rows: usize, cols: usize) {
+    let title = format!("[ {} ]", strings.get("error"));
+    render_notice(&title, 3, error, strings, rows, cols);
+}
+
+/// Render the result screen shown for a while after closing panes or tabs
+fn render_result(result: &str, strings: &Strings, rows: usize, cols: usize) {
+    let title = format!("[ {} ]", strings.get("done"));
+    render_notice(&title, 2, result, strings, rows, cols);
+}
+
+/// Render a screen with just a title in the given emphasis color, a message, and how to dismiss it
+fn render_notice(
+    title: &str,
+    title_color: usize,
+    message: &str,
+    strings: &Strings,
+    rows: usize,
+    cols: usize,
+) {
+    let title_x = cols.saturating_sub(display_width(title)) / 2;
+    print_text_with_coordinates(
+        Text::new(title).color_range(title_color, 0..title.chars().count()),
+        title_x,
+        (rows / 2).saturating_sub(2),
+        None,
+        None,
+    );
+
+    let message_x = cols.saturating_sub(display_width(message)) / 2;
+    print_text_with_coordinates(Text::new(message), message_x, rows / 2, None, None);
+
+    let help_text = strings.get("dismiss");
+    let help_text_x = cols.saturating_sub(display_width(help_text)) / 2;
+    print_text_with_coordinates(
+        Text::new(help_text),
+        help_text_x,
+        rows.saturating_sub(1),
+        None,
+        None,
+    );
+}
+
+/// Render the list of configuration problems found on load, one per line
+fn render_config_problems(problems: &[String], strings: &Strings, rows: usize, cols: usize) {
+    let title = format!("[ {} ]", strings.get("config_problems"));
+    // The title, a blank row and the help line
+    let max_lines = rows.saturating_sub(5).max(1);
+    let mut lines: Vec<String> = problems.iter().take(max_lines).cloned().collect();
+    if problems.len() > max_lines {
+        // The last row that fits tells how many problems didn't
+        lines.pop();
+        let hidden = (problems.len() - max_lines + 1).to_string();
+        lines.push(strings.format("more", &[("count", &hidden)]));
+    }
+    let top = rows.saturating_sub(lines.len() + 2) / 2;
+    let title_x = cols.saturating_sub(display_width(&title)) / 2;
+    print_text_with_coordinates(
+        Text::new(&title).color_range(3, 0..title.chars().count()),
+        title_x,
+        top,
+        None,
+        None,
+    );
+    let width = lines
+        .iter()
+        .map(|line| display_width(line))
+        .max()
+        .unwrap_or(0)
+        .min(cols);
+    // Left-aligned in a centered block, so the list reads as one
+    let x = cols.saturating_sub(width) / 2;
+    for (index, line) in lines.iter().enumerate() {
+        let line = truncate_to_width(line, cols);
+        print_text_with_coordinates(Text::new(line), x, top + 2 + index, None, None);
+    }
+
+    let help_text = strings.get("dismiss");
+    let help_text_x = cols.saturating_sub(display_width(help_text)) / 2;
+    print_text_with_coordinates(
+        Text::new(help_text),
+        help_text_x,
+        rows.saturating_sub(1),
+        None,
+        None,
+    );
+}
+
+/// A row of the dialog body, between the title and the lists
+enum DialogLine {
+    Blank,
+    Text(String),
+    /// Text whose prefix of the given length is in the given emphasis color
+    Prefixed(String, usize, usize),
+    /// Text in the theme's error color
+    Danger(String),
+    /// Text on a block of the theme's error color
+    DangerBlock(String),
+    /// The Confirm and Cancel buttons
+    Buttons,
+    /// The field showing what was typed so far in a typed confirmation
+    Input,
+}
+
+/// What is listed below the dialog body, in the rows left over
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DialogList {
+    None,
+    /// The idle panes CloseIdlePanes picks from
+    IdlePanes,
+    /// The session's tabs and their pane counts, before quitting it
+    SessionTabs,
+    /// The panes of the target tab, before closing it
+    TabPanes,
+    /// The target pane's processes, with `show_processes`
+    Processes,
+}
+
+/// Where the parts of the dialog go in a pane of a given height
+///
+/// Everything is centered above the help line. When the pane is too short the title goes first,
+/// then the help line, and only then body lines are cut off. Lists get whatever rows are left.
+struct DialogLayout {
+    /// The row of the first line inside the border
+    top: usize,
+    show_title: bool,
+    /// How many body lines fit
+    body_rows: usize,
+    /// How many list entries fit below the body
+    list_rows: usize,
+    show_help: bool,
+}
+
+impl DialogLayout {
+    /// Rows taken by the border above and below the dialog
+    const BORDER_ROWS: usize = 2;
+    /// Rows taken by the title and the blank line below it
+    const TITLE_ROWS: usize = 2;
+    /// The help line below the border
+    const HELP_ROWS: usize = 1;
+
+    fn new(rows: usize, body_len: usize, list_len: usize) -> Self {
+        let inner_rows = rows.saturating_sub(Self::BORDER_ROWS);
+        let show_title = inner_rows >= Self::TITLE_ROWS + body_len + Self::HELP_ROWS;
+        let show_help = inner_rows >= body_len + Self::HELP_ROWS;
+        let title_rows = if show_title { Self::TITLE_ROWS } else { 0 };
+        let help_rows = if show_help { Self::HELP_ROWS } else { 0 };
+        let available = inner_rows - title_rows - help_rows;
+        let body_rows = body_len.min(available);
+        // The list is separated from the body by a blank line
+        let list_rows = list_len.min(available.saturating_sub(body_rows + 1));
+        let list_height = if list_rows > 0 { list_rows + 1 } else { 0 };
+        let free_rows = available - body_rows - list_height;
+        DialogLayout {
+            top: 1 + free_rows / 2,
+            show_title,
+            body_rows,
+            list_rows,
+            show_help,
+        }
+    }
+}
+
+/// The characters the dialog's border is drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum BorderStyle {
+    #[default]
+    Single,
+    Rounded,
+    Double,
+    /// Plain ASCII, for terminals and fonts without box-drawing characters
+    Ascii,
+    /// No border at all
+    None,
+}
+
+impl BorderStyle {
+    pub(crate) fn from_config(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "single" => Some(BorderStyle::Single),
+            "rounded" => Some(BorderStyle::Rounded),
+            "double" => Some(BorderStyle::Double),
+            "ascii" => Some(BorderStyle::Ascii),
+            "none" => Some(BorderStyle::None),
+            _ => None,
+        }
+    }
+
+    /// The horizontal and vertical lines, then the top left, top right, bottom left and bottom
+    /// right corners
+    fn chars(&self) -> Option<[char; 6]> {
+        match self {
+            BorderStyle::Single => Some(['─', '│', '┌', '┐', '└', '┘']),
+            BorderStyle::Rounded => Some(['─', '│', '╭', '╮', '╰', '╯']),
+            BorderStyle::Double => Some(['═', '║', '╔', '╗', '╚', '╝']),
+            BorderStyle::Ascii => Some(['-', '|', '+', '+', '+', '+']),
+            BorderStyle::None => None,
+        }
+    }
+}
+
+/// The extent of the dialog's lines, grown line by line so the border can be drawn around them
+struct DialogBox {
+    top: usize,
+    bottom: usize,
+    width: usize,
+}
+
+impl DialogBox {
+    /// Columns taken by the border and its padding on both sides of the lines
+    const MARGIN_COLS: usize = 6;
+
+    fn new(top: usize) -> Self {
+        DialogBox {
+            top,
+            bottom: top,
+            width: 0,
+        }
+    }
+
+    /// Make room for a line of `width` characters at row `y`
+    fn include(&mut self, width: usize, y: usize) {
+        self.width = self.width.max(width);
+        self.bottom = self.bottom.max(y);
+    }
+
+    /// The column of the left border and the width between the borders
+    fn frame(&self, cols: usize) -> (usize, usize) {
+        let inner_width = (self.width + Self::MARGIN_COLS - 2).min(cols.saturating_sub(2));
+        (cols.saturating_sub(inner_width + 2) / 2, inner_width)
+    }
+
+    /// Draw `text` centered on a danger colored bar filling the box from border to border
+    fn draw_bar(&self, text: &str, y: usize, cols: usize, styling: Option<&Styling>) {
+        let (x, inner_width) = self.frame(cols);
+        let left = inner_width.saturating_sub(display_width(text)) / 2;
+        let right = inner_width.saturating_sub(display_width(text) + left);
+        let bar = format!("{}{}{}", " ".repeat(left), text, " ".repeat(right));
+        print_danger_block(&bar, x + 1, y, styling);
+    }
+
+    /// Draw the border one row above and below the lines, with a space of padding at the sides,
+    /// in the theme's frame color when it is known
+    fn draw(&self, cols: usize, styling: Option<&Styling>, border: BorderStyle) {
+        let Some([horizontal, vertical, top_left, top_right, bottom_left, bottom_right]) =
+            border.chars()
+        else {
+            return;
+        };
+        if let Some(styling) = styling {
+            let frame = styling.frame_unselected.unwrap_or(styling.text_unselected);
+            print!("{}", ansi_foreground(frame.base));
+        }
+        let (x, inner_width) = self.frame(cols);
+        let horizontal = horizontal.to_string().repeat(inner_width);
+        let top_y = self.top.saturating_sub(1);
+        print!(
+            "\u{1b}[{};{}H{}{}{}",
+            top_y + 1,
+            x + 1,
+            top_left,
+            horizontal,
+            top_right
+        );
+        for y in self.top..=self.bottom {
+            print!("\u{1b}[{};{}H{}", y + 1, x + 1, vertical);
+            print!("\u{1b}[{};{}H{}", y + 1, x + inner_width + 2, vertical);
+        }
+        print!(
+            "\u{1b}[{};{}H{}{}{}\u{1b}[0m",
+            self.bottom + 2,
+            x + 1,
+            bottom_left,
+            horizontal,
+            bottom_right
+        );
+    }
 }
 
+/// Delay between interrupting two panes in InterruptAll, long enough to see the progress
+const INTERRUPT_INTERVAL_SECS: f64 = 0.05;
+
 register_plugin!(State);
+register_worker!(ProcessWorker, processes_worker, PROCESSES_WORKER);
 
 impl ZellijPlugin for State {
     fn load(&mut self, configuration: BTreeMap<String, String>) {
-        request_permission(&[
-            PermissionType::ChangeApplicationState,
-            PermissionType::ReadApplicationState,
-        ]);
-        subscribe(&[EventType::Key, EventType::PaneUpdate, EventType::TabUpdate]);
-
-        // Parse action from configuration
-        if let Some(action_str) = configuration.get("action") {
-            self.action = Action::from_config(action_str);
+        self.block_configuration = config::normalize(configuration);
+        self.load_configuration();
+        if self.config_file_path.is_some() {
+            // Edits to the file apply without reloading the plugin
+            watch_filesystem();
         }
+
+        self.request_permissions();
+        subscribe(&[
+            EventType::Key,
+            EventType::CustomMessage,
+            EventType::Timer,
+            EventType::RunCommandResult,
+            EventType::Visible,
+            EventType::Mouse,
+            EventType::PermissionRequestResult,
+            EventType::FileSystemCreate,
+            EventType::FileSystemUpdate,
+        ]);
+        self.plugin_id = get_plugin_ids().plugin_id;
+        // The dialog shown on load isn't opened through `open_dialog`
+        self.focused_button = self.default_button;
+        self.start_countdown();
     }
 
     fn update(&mut self, event: Event) -> bool {
+        if matches!(event, Event::Key(_)) && self.countdown_deadline.is_some() {
+            // The countdown is for forgotten dialogs, not ones being answered
+            self.start_countdown();
+        }
         match event {
+            Event::Key(_) if !self.config_problems.is_empty() => {
+                // Any key dismisses them, the dialog goes on with the defaults in their place
+                self.config_problems.clear();
+            }
+            Event::Key(_) if self.error.is_some() => {
+                // Any key dismisses the error screen
+                self.error = None;
+                hide_self();
+                if std::mem::take(&mut self.quit_after_error) {
+                    // Still ends the session, just without deleting it
+                    quit_zellij();
+                }
+            }
+            Event::Key(_) if self.result.is_some() => {
+                // Any key dismisses the result screen early too
+                self.dismiss_result();
+            }
+            Event::Key(_) if self.quit_deadline.is_some() => {
+                // Any key takes the confirmation back while the quit is still counting down
+                self.quit_deadline = None;
+                self.cancel();
+            }
+            // Ignore keys while a confirmed chain is still running, or while the dialog is still
+            // blank waiting to know whether it's needed
+            Event::Key(_) if self.is_busy() || self.skip_check_pending => {}
+            Event::Key(key) if self.requires_typed_confirmation() => self.handle_typed_key(key),
+            Event::Key(key) if self.is_idle_list_key(&key) => self.handle_idle_list_key(key),
+            Event::Key(key) if self.is_tab_pane_scroll_key(&key) => {
+                self.handle_tab_pane_scroll_key(key)
+            }
             Event::Key(key) => {
-                if is_confirm(&key) {
-                    self.execute_action();
+                if self.is_button_switch(&key) {
+                    self.focused_button = self.focused_button.other();
+                } else if self.is_confirm(&key) {
+                    // Before Enter, which may be configured as a confirm key too
+                    self.confirm();
+                } else if key.bare_key == BareKey::Enter && key.has_no_modifiers() {
+                    self.press(self.focused_button);
+                } else if let Some(alternative) = self.alternative_for(&key) {
+                    self.pick_alternative(alternative);
+                } else if self.is_cancel(&key) || self.any_key_cancels {
+                    self.confirm_armed = false;
+                    self.armed_alternative = None;
+                    self.cancel();
                 } else {
-                    // Any key other than y/Y cancels (following [y/N] convention)
-                    hide_self();
+                    self.flash_key_hint();
                 }
             }
+            Event::Mouse(_) if !self.shows_dialog() => {}
+            Event::Mouse(Mouse::LeftClick(line, column)) => {
+                if let Some(button) = self.button_at(line, column) {
+                    self.focused_button = button;
+                    self.press(button);
+                }
+            }
+            Event::Mouse(Mouse::Hover(line, column)) => {
+                self.hovered_button = self.button_at(line, column);
+            }
             Event::PaneUpdate(pane_manifest) => {
-                // Only capture the target pane once (when plugin first opens)
-                if !self.pane_info_received && self.action == Action::ClosePane {
-                    // Find the focused non-plugin pane in the current tab
-                    if let Some(tab_index) = self.target_tab_index {
-                        if let Some(pane_info) = get_focused_pane(tab_index, &pane_manifest) {
-                            self.target_pane_id = if pane_info.is_plugin {
-                                Some(PaneId::Plugin(pane_info.id))
-                            } else {
-                                Some(PaneId::Terminal(pane_info.id))
-                            };
-                        }
+                self.panes = pane_manifest;
+                self.track_command_panes();
+                self.capture_targets();
+                self.follow_focus();
+                self.resolve_target_tab();
+                self.dismiss_if_target_gone();
+            }
+            Event::TabUpdate(tab_infos) => {
+                self.tabs = tab_infos;
+                self.capture_targets();
+                self.follow_focus();
+            }
+            Event::ModeUpdate(mode_info) => {
+                self.styling = Some(mode_info.style.colors);
+                self.current_session_name = mode_info.session_name;
+            }
+            Event::Visible(visible) => {
+                self.visible = visible;
+                if !visible {
+                    if self.quit_deadline.take().is_some() {
+                        // Hidden while the quit counts down, with nothing left on screen to take
+                        // it back, so it is taken back like by a key
+                        self.decide("cancelled");
+                    } else if !self.answered {
+                        // Hidden some other way than its own keys, e.g. by toggling the floating
+                        // panes, which a waiting `zellij pipe` or plugin takes as a no
+                        self.report_decision("cancelled");
                     }
-                    self.pane_info_received = true;
+                    // Overrides only last for the invocation that passed them
+                    self.restore_configuration();
+                    self.countdown_deadline = None;
+                } else if self.countdown_deadline.is_none() {
+                    // Shown again by its keybind
+                    self.start_countdown();
                 }
             }
-            Event::TabUpdate(tab_infos) => {
-                // Capture the focused tab index when plugin opens
-                if self.target_tab_index.is_none() {
-                    if let Some(focused_tab) = get_focused_tab(&tab_infos) {
-                        self.target_tab_index = Some(focused_tab.position);
+            Event::FileSystemCreate(paths) | Event::FileSystemUpdate(paths)
+                if paths.iter().any(|(path, _)| self.is_config_file(path)) =>
+            {
+                // The open dialog keeps asking about the same thing
+                let action = self.action.clone();
+                self.load_configuration();
+                self.action = action;
+                self.request_permissions();
+            }
+            Event::PermissionRequestResult(status) => {
+                self.permissions_denied = status == PermissionStatus::Denied;
+                if self.permissions_denied {
+                    // No scan is coming to decide whether the dialog is needed
+                    self.skip_check_pending = false;
+                    self.skip_check_deadline = None;
+                } else {
+                    // Without the session's state this is the only event to start from
+                    self.capture_targets();
+                }
+            }
+            Event::SessionUpdate(session_infos, _resurrectable_sessions) => {
+                self.sessions = session_infos;
+            }
+            Event::Timer(_) if self.interrupt_due.is_some_and(|due| Instant::now() >= due) => {
+                self.interrupt_next_pane();
+            }
+            Event::Timer(_) => self.tick_countdown(),
+            Event::RunCommandResult(_exit_code, stdout, _stderr, context)
+                if context.contains_key(processes::WORKER_NAME) =>
+            {
+                // Building the tree is left to the worker
+                post_message_to(PluginMessage::new_to_worker(
+                    processes::WORKER_NAME,
+                    processes::PARSE_MESSAGE,
+                    &String::from_utf8_lossy(&stdout),
+                ));
+            }
+            // Deleting the session takes the plugin down with it, so only a failure gets here
+            Event::RunCommandResult(exit_code, _stdout, stderr, context)
+                if context.contains_key(DELETE_SESSION_CONTEXT) && exit_code != Some(0) =>
+            {
+                let error = String::from_utf8_lossy(&stderr).trim().to_string();
+                let name = context
+                    .get(DELETE_SESSION_CONTEXT)
+                    .cloned()
+                    .unwrap_or_default();
+                self.fail(self.strings.format(
+                    "error.delete_session",
+                    &[("name", &name), ("error", &error)],
+                ));
+                self.quit_after_error = true;
+            }
+            Event::RunCommandResult(_exit_code, stdout, _stderr, context)
+                if context.contains_key(processes::GIT_STATUS_CONTEXT) =>
+            {
+                self.dirty_repos = String::from_utf8_lossy(&stdout)
+                    .lines()
+                    .map(String::from)
+                    .collect();
+            }
+            Event::RunCommandResult(_exit_code, stdout, _stderr, context)
+                if context.contains_key(processes::IDLE_CONTEXT) =>
+            {
+                post_message_to(PluginMessage::new_to_worker(
+                    processes::WORKER_NAME,
+                    processes::PARSE_IDLE_MESSAGE,
+                    &String::from_utf8_lossy(&stdout),
+                ));
+            }
+            Event::RunCommandResult(_exit_code, stdout, _stderr, context)
+                if context.contains_key(processes::COMMANDS_CONTEXT) =>
+            {
+                post_message_to(PluginMessage::new_to_worker(
+                    processes::WORKER_NAME,
+                    processes::PARSE_COMMANDS_MESSAGE,
+                    &String::from_utf8_lossy(&stdout),
+                ));
+            }
+            Event::CustomMessage(message, payload) if message == processes::COMMANDS_MESSAGE => {
+                self.running_commands.clear();
+                self.child_process_counts.clear();
+                for line in payload.lines() {
+                    let mut fields = line.splitn(3, ' ');
+                    let (Some(pane_id), Some(children), Some(args)) =
+                        (fields.next(), fields.next(), fields.next())
+                    else {
+                        continue;
+                    };
+                    let (Ok(pane_id), Ok(children)) = (pane_id.parse(), children.parse()) else {
+                        continue;
+                    };
+                    let pane_id = PaneId::Terminal(pane_id);
+                    self.running_commands.insert(pane_id, args.to_string());
+                    self.child_process_counts.insert(pane_id, children);
+                }
+                if self.skip_check_pending {
+                    self.skip_check_pending = false;
+                    self.skip_check_deadline = None;
+                    if self.should_skip_dialog() {
+                        self.execute_action();
                     }
                 }
             }
+            Event::CustomMessage(message, payload) if message == processes::IDLE_MESSAGE => {
+                self.idle_scan_pending = false;
+                let idle_ids: Vec<u32> = payload
+                    .split_whitespace()
+                    .filter_map(|id| id.parse().ok())
+                    .collect();
+                // Command panes start with their command rather than a shell, so they never
+                // count as idle
+                self.idle_panes = self
+                    .panes
+                    .panes
+                    .values()
+                    .flatten()
+                    .filter(|pane| !pane.is_plugin && pane.terminal_command.is_none())
+                    .filter(|pane| idle_ids.contains(&pane.id))
+                    .map(|pane| (PaneId::Terminal(pane.id), true))
+                    .collect();
+                self.idle_cursor = 0;
+            }
+            Event::CustomMessage(message, payload) if message == processes::RESULT_MESSAGE => {
+                self.target_processes = payload.lines().map(String::from).collect();
+            }
+            Event::CustomMessage(message, payload) if message == processes::FOREGROUND_MESSAGE => {
+                self.foreground_elapsed = payload
+                    .split_once(' ')
+                    .and_then(|(elapsed, args)| Some((elapsed.parse().ok()?, args.to_string())));
+            }
+            Event::CustomMessage(message, payload) if self.awaiting_layout => {
+                self.awaiting_layout = false;
+                match message.as_str() {
+                    "session_layout" => match self.save_layout(&payload) {
+                        Ok(()) => self.run_pending_steps(),
+                        Err(error) => self.fail(error),
+                    },
+                    _ => self.fail(
+                        self.strings
+                            .format("error.dump_layout", &[("error", &payload)]),
+                    ),
+                }
+            }
             _ => (),
         };
         // Return true to trigger re-render after receiving pane/tab info
         true
     }
 
-    fn render(&mut self, rows: usize, cols: usize) {
-        // Title line showing what action will be performed
-        let title = format!("[ {} ]", self.action.action_name());
-        let title_y = (rows / 2) - 3;
-        let title_x = cols.saturating_sub(title.chars().count()) / 2;
-
-        print_text_with_coordinates(
-            Text::new(&title).color_range(2, 0..title.chars().count()),
-            title_x,
-            title_y,
-            None,
-            None,
-        );
+    fn pipe(&mut self, pipe_message: PipeMessage) -> bool {
+        // e.g. `zellij pipe --plugin zj-quit -- action=close_tab`, or just `-- close_tab`
+        if !self.is_on_channel(&pipe_message) {
+            return false;
+        }
+        let payload = pipe_message.payload.as_deref().unwrap_or_default();
+        let mut words = payload.split_whitespace();
+        if pipe_message.name == "reload_config" || words.next() == Some("reload_config") {
+            // e.g. `zellij pipe --plugin zj-quit -- reload_config action=close_tab`
+            let pairs = words
+                .filter_map(|word| word.split_once('='))
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .chain(pipe_message.args.clone())
+                .filter(|(key, _)| key != "channel")
+                .collect();
+            self.reload_configuration(pairs);
+            return true;
+        }
+        if pipe_message.name == "print-keybinds" || payload.trim() == "print-keybinds" {
+            // e.g. `zellij pipe --plugin zj-quit --args "key=Alt q" -- print-keybinds`
+            let PipeSource::Cli(pipe_id) = &pipe_message.source else {
+                return false;
+            };
+            let key = pipe_message
+                .args
+                .get("key")
+                .map_or(DEFAULT_KEYBIND, String::as_str);
+            let snippet = config::keybind_snippet(&self.block_configuration, key);
+            cli_pipe_output(pipe_id, &snippet);
+            unblock_cli_pipe_input(pipe_id);
+            return false;
+        }
+        if pipe_message.name == "toggle" || payload.trim() == "toggle" {
+            // One keybind to both open and dismiss the dialog
+            if self.visible {
+                self.cancel();
+            } else {
+                self.open_dialog(DialogRequest::new(self.action.clone()));
+            }
+            return true;
+        }
+        let request = if pipe_message.name == "confirm" {
+            // A question from a script or another plugin, e.g. `zellij pipe --name confirm
+            // --plugin zj-quit -- "Deploy to production?"`
+            DialogRequest {
+                title: pipe_message.args.get("title").cloned(),
+                message: Some(payload.to_string()).filter(|message| !message.is_empty()),
+                ..DialogRequest::new(Action::Confirm)
+            }
+        } else if let Ok(json) = serde_json::from_str::<PipePayload>(payload) {
+            let action = match &json.action {
+                Some(action) => Action::from_config(action),
+                None => self.action.clone(),
+            };
+            DialogRequest {
+                title: json.title,
+                message: json.message,
+                target_pane_id: json.id.as_deref().and_then(parse_pane_id),
+                ..DialogRequest::new(action)
+            }
+        } else {
+            // Words are either `key=value` pairs or the action itself
+            let pair = |key: &str| {
+                payload
+                    .split_whitespace()
+                    .find_map(|word| word.strip_prefix(key)?.strip_prefix('='))
+                    .or_else(|| pipe_message.args.get(key).map(String::as_str))
+            };
+            let action = pair("action").map(Action::from_config).or_else(|| {
+                payload
+                    .split_whitespace()
+                    .find(|word| !word.contains('='))
+                    .and_then(Action::parse)
+            });
+            let Some(action) = action else {
+                return false;
+            };
+            DialogRequest {
+                target_pane_id: pair("id").and_then(parse_pane_id),
+                ..DialogRequest::new(action)
+            }
+        };
+
+        // A `zellij pipe` still waiting on the dialog being replaced gets its answer now
+        self.report_decision("cancelled");
+        self.restore_configuration();
+        // A named profile goes first, so single arguments can still override it
+        let profile = pipe_message
+            .args
+            .get("profile")
+            .map(String::as_str)
+            .or_else(|| {
+                payload
+                    .split_whitespace()
+                    .find_map(|word| word.strip_prefix("profile="))
+            });
+        let mut overrides = BTreeMap::new();
+        if let Some(profile) = profile {
+            overrides = config::profile(&self.configuration, profile);
+            if overrides.is_empty() {
+                self.config_problems.push(format!(
+                    "profile: no profile.{}.* keys are configured",
+                    profile
+                ));
+            }
+        }
+        overrides.extend(
+            pipe_message
+                .args
+                .iter()
+                .map(|(key, value)| (config::normalize_key(key), value.clone()))
+                .filter(|(key, _)| !RESERVED_PIPE_ARGS.contains(&key.as_str())),
+        );
+        if !overrides.is_empty() {
+            let mut configuration = self.configuration.clone();
+            configuration.extend(overrides);
+            self.apply_configuration(&configuration);
+            self.configuration_overridden = true;
+        }
+        self.requester = match &pipe_message.source {
+            PipeSource::Cli(pipe_id) => {
+                // Hold the pipe open until the user decides, so scripts can branch on the result
+                block_cli_pipe_input(pipe_id);
+                Some(Requester::CliPipe(pipe_id.clone()))
+            }
+            PipeSource::Plugin(plugin_id) if request.action == Action::Confirm => {
+                if !self.answers_plugins {
+                    // Only asked for once a plugin is waiting on an answer
+                    self.answers_plugins = true;
+                    self.request_permissions();
+                }
+                Some(Requester::Plugin {
+                    plugin_id: *plugin_id,
+                    request_id: pipe_message.args.get("request_id").cloned(),
+                })
+            }
+            _ => None,
+        };
+        self.open_dialog(request);
+        true
+    }
+
+    fn render(&mut self, rows: usize, cols: usize) {
+        if !self.config_problems.is_empty() {
+            render_config_problems(&self.config_problems, &self.strings, rows, cols);
+            return;
+        }
+        if let Some(error) = &self.error {
+            render_error(error, &self.strings, rows, cols);
+            return;
+        }
+        if let Some(result) = &self.result {
+            render_result(result, &self.strings, rows, cols);
+            return;
+        }
+        if self.skip_check_pending {
+            // Stay blank until we know whether the dialog is needed at all
+            return;
+        }
+        if let Some(quit_deadline) = self.quit_deadline {
+            let remaining = quit_deadline.saturating_duration_since(Instant::now());
+            let (ellipsis, dash) = if self.ascii_only {
+                ("...", "-")
+            } else {
+                ("…", "—")
+            };
+            let seconds = remaining.as_millis().div_ceil(1000).to_string();
+            let countdown = self.strings.format(
+                "quitting",
+                &[
+                    ("seconds", &seconds),
+                    ("ellipsis", ellipsis),
+                    ("dash", dash),
+                ],
+            );
+            let countdown_x = cols.saturating_sub(display_width(&countdown)) / 2;
+            print_danger_text(&countdown, countdown_x, rows / 2, self.styling.as_ref());
+            return;
+        }
+        if let Some(pane_id) = self.interrupt_queue.front() {
+            let done = self.interrupt_total - self.interrupt_queue.len();
+            let progress = self.strings.format(
+                "interrupting",
+                &[
+                    ("done", &(done + 1).to_string()),
+                    ("total", &self.interrupt_total.to_string()),
+                    ("pane", &pane_id_number(pane_id).to_string()),
+                ],
+            );
+            let progress_x = cols.saturating_sub(display_width(&progress)) / 2;
+            print_text_with_coordinates(Text::new(progress), progress_x, rows / 2, None, None);
+            return;
+        }
+
+        let action = self.dialog_action();
+        let escalation_reason = self.escalation_reason();
+        // Ending the session or an escalated dialog gets a visual speed bump
+        let dangerous = escalation_reason.is_some() || self.ends_session();
+
+        let mut title = match &self.custom_title {
+            Some(title) => self.fill_placeholders(title),
+            None => action.title(&self.strings),
+        };
+        if self.icons && !self.ascii_only {
+            title = format!("{} {}", action.icon(), title);
+        }
+        if rows < MIN_DIALOG_ROWS {
+            self.render_compact(&title, dangerous, rows, cols);
+            return;
+        }
+
+        // The body of the dialog, everything between the title and the lists
+        let mut lines = Vec::new();
+        // The confirmation text, wrapped to fit inside the border
+        let text_width = self.content_width(cols);
+        for line in self.confirmation_text(&action).lines() {
+            for wrapped in wrap_text(line, text_width) {
+                lines.push(DialogLine::Text(wrapped));
+            }
+        }
+        lines.push(DialogLine::Blank);
+        // Show target info for pane/tab close
+        let target_info = self.target_info(&action);
+        if !target_info.is_empty() {
+            let mut prefix = self.strings.get("target").to_string();
+            if self.icons && !self.ascii_only {
+                prefix = format!("{} {}", TARGET_ICON, prefix);
+            }
+            // Color the prefix, with the icon if any
+            let prefix_len = prefix.chars().count();
+            let target_info = format!("{} {}", prefix, target_info);
+            lines.push(DialogLine::Prefixed(
+                target_info,
+                self.target_color,
+                prefix_len,
+            ));
+        }
+        // Warnings about what else the action takes down, one per line
+        let warning_prefix = self.strings.get("warning");
+        for warning in self.warnings() {
+            let warning = format!("{} {}", warning_prefix, warning);
+            let prefix_len = warning_prefix.chars().count();
+            lines.push(DialogLine::Prefixed(
+                warning,
+                self.warning_color,
+                prefix_len,
+            ));
+        }
+        // Dropping a remote connection is easy to miss and hard to undo, so these stand out
+        for connection in self.remote_connections() {
+            lines.push(DialogLine::Danger(
+                self.strings
+                    .format("remote_connection", &[("name", connection)]),
+            ));
+        }
+        if let Some(reason) = &escalation_reason {
+            lines.push(DialogLine::DangerBlock(format!(" {} ", reason)));
+        }
+        // The buttons, or the input field when the dialog wants the confirmation word typed out
+        lines.push(DialogLine::Blank);
+        if self.requires_typed_confirmation() {
+            lines.push(DialogLine::Input);
+        } else {
+            lines.push(DialogLine::Buttons);
+        }
+        if let Some(remaining) = self.countdown_remaining() {
+            let ellipsis = if self.ascii_only { "..." } else { "…" };
+            let id = if self.countdown_confirms() {
+                "auto_confirm"
+            } else {
+                "auto_cancel"
+            };
+            lines.push(DialogLine::Text(self.strings.format(
+                id,
+                &[("seconds", &remaining.to_string()), ("ellipsis", ellipsis)],
+            )));
+        }
+
+        let list = self.dialog_list(&action);
+        let list_len = match list {
+            DialogList::None => 0,
+            DialogList::IdlePanes => self.idle_panes.len(),
+            // The table has a header row
+            DialogList::SessionTabs => self.tabs.len() + 1,
+            DialogList::TabPanes => self.target_tab_pane_entries().len(),
+            DialogList::Processes => self.target_processes.len(),
+        };
+        let mut layout = DialogLayout::new(rows, lines.len(), list_len);
+        if layout.body_rows < lines.len() {
+            // Blank lines are the first body lines to go
+            lines.retain(|line| !matches!(line, DialogLine::Blank));
+            layout = DialogLayout::new(rows, lines.len(), list_len);
+        }
+
+        let mut dialog_box = DialogBox::new(layout.top);
+        let mut y = layout.top;
+        // Dangerous dialogs get their title on a red bar across the box, drawn once the width of
+        // the box is known
+        let mut title_bar = None;
+        if layout.show_title {
+            // Title line showing what action will be performed
+            let title = format!("[ {} ]", title);
+            let title_x = cols.saturating_sub(display_width(&title)) / 2;
+            dialog_box.include(display_width(&title), y);
+            if dangerous {
+                title_bar = Some((title, y));
+            } else {
+                print_text_with_coordinates(
+                    Text::new(&title).color_range(self.title_color, 0..title.chars().count()),
+                    title_x,
+                    y,
+                    None,
+                    None,
+                );
+            }
+            y += 2;
+        }
+
+        self.button_positions.clear();
+        for line in lines.iter().take(layout.body_rows) {
+            self.render_line(line, &mut dialog_box, y, cols);
+            y += 1;
+        }
+
+        // The list, in the rows left below the body
+        let list_y = y + 1;
+        match list {
+            DialogList::None => {}
+            DialogList::IdlePanes => {
+                self.render_idle_list(&mut dialog_box, list_y, layout.list_rows, cols)
+            }
+            DialogList::SessionTabs => {
+                self.render_tab_table(&mut dialog_box, list_y, layout.list_rows, cols)
+            }
+            DialogList::TabPanes => {
+                self.render_tab_panes(&mut dialog_box, list_y, layout.list_rows, cols)
+            }
+            DialogList::Processes => {
+                self.render_processes(&mut dialog_box, list_y, layout.list_rows, cols)
+            }
+        }
+        if let Some((title, title_y)) = title_bar {
+            dialog_box.draw_bar(&title, title_y, cols, self.styling.as_ref());
+        }
+        dialog_box.draw(cols, self.styling.as_ref(), self.border_style());
+
+        if !layout.show_help {
+            return;
+        }
+        if self.requires_typed_confirmation() {
+            let word = self.confirmation_word();
+            let (prompt, word_ranges) = self.strings.format_with_ranges(
+                "typed_prompt",
+                &[("word", &word), ("enter", "Enter"), ("cancel", "Esc")],
+            );
+            let prompt_x = cols.saturating_sub(display_width(&prompt)) / 2;
+            print_text_with_coordinates(
+                color_ranges(Text::new(&prompt), 3, word_ranges),
+                prompt_x,
+                rows - 1,
+                None,
+                None,
+            );
+            return;
+        }
+
+        let confirm_label = self.confirm_key_label();
+        let cancel_label = self.cancel_key_label();
+        let key_labels = [
+            ("confirm", confirm_label.as_str()),
+            ("cancel", cancel_label.as_str()),
+            // Enter presses the focused button, which an armed dialog moves to Confirm
+            ("enter", "Enter"),
+        ];
+        if self.key_hint_deadline.is_some() {
+            // Flashed in place of the help line when a key does nothing, so the dialog doesn't
+            // look frozen
+            let (hint, key_ranges) = self.strings.format_with_ranges("key_hint", &key_labels);
+            let hint_x = cols.saturating_sub(display_width(&hint)) / 2;
+            print_text_with_coordinates(
+                color_ranges(Text::new(&hint), 3, key_ranges),
+                hint_x,
+                rows - 1,
+                None,
+                None,
+            );
+            return;
+        }
+
+        if let Some((alternative, key)) = self.armed_alternative_key() {
+            let (help_text, key_ranges) = self.strings.format_with_ranges(
+                "armed_alternative",
+                &[
+                    ("key", &key),
+                    ("alternative", self.strings.get(alternative.hint_id())),
+                    ("cancel", &cancel_label),
+                ],
+            );
+            let help_text_x = cols.saturating_sub(display_width(&help_text)) / 2;
+            print_text_with_coordinates(
+                color_ranges(Text::new(&help_text), 3, key_ranges),
+                help_text_x,
+                rows - 1,
+                None,
+                None,
+            );
+            return;
+        }
+
+        if self.confirm_armed {
+            let (help_text, key_ranges) = self.strings.format_with_ranges("armed", &key_labels);
+            let help_text_x = cols.saturating_sub(display_width(&help_text)) / 2;
+            print_text_with_coordinates(
+                color_ranges(Text::new(&help_text), 3, key_ranges),
+                help_text_x,
+                rows - 1,
+                None,
+                None,
+            );
+            return;
+        }
+
+        if !self.show_help {
+            return;
+        }
+        if let Some(help_text) = &self.help_text {
+            let (help_text, key_ranges) = strings::fill(
+                help_text,
+                &[
+                    ("confirm_key", &confirm_label),
+                    ("cancel_key", &cancel_label),
+                ],
+            );
+            let help_text = truncate_to_width(&help_text, cols);
+            let help_text_x = cols.saturating_sub(display_width(&help_text)) / 2;
+            print_text_with_coordinates(
+                color_ranges(Text::new(&help_text), self.key_hint_color, key_ranges),
+                help_text_x,
+                rows - 1,
+                None,
+                None,
+            );
+            return;
+        }
+
+        // Help ribbons at the bottom - [y/N] convention, plus the action's alternatives if any
+        let mut hints: Vec<(String, &str)> = vec![
+            (confirm_label.clone(), "hint.confirm"),
+            (cancel_label.clone(), "hint.cancel"),
+        ];
+        hints.extend(
+            self.alternatives()
+                .into_iter()
+                .map(|(alternative, key)| (key.to_string(), alternative.hint_id())),
+        );
+        if !self.idle_panes.is_empty() {
+            hints.push((self.arrows("↑/↓", "Up/Down"), "hint.move"));
+            hints.push(("Space".to_string(), "hint.toggle"));
+        }
+        if self.dialog_list(&self.dialog_action()) == DialogList::TabPanes
+            && self.target_tab_pane_entries().len() > self.tab_pane_rows
+        {
+            hints.push((self.arrows("↑/↓", "Up/Down"), "hint.scroll"));
+        }
+        let mut select_keys = self.arrows("←/→", "Left/Right");
+        if self.vim_keys {
+            select_keys.push_str("/h/l");
+        }
+        hints.push((select_keys, "hint.select"));
+        hints.push(("Enter".to_string(), "hint.press"));
+        let mut ribbons: Vec<(String, usize)> = hints
+            .into_iter()
+            .map(|(key, id)| {
+                let key_len = key.chars().count();
+                (format!("{} {}", key, self.strings.get(id)), key_len)
+            })
+            .collect();
+        let ribbons_width = |ribbons: &[(String, usize)]| -> usize {
+            ribbons
+                .iter()
+                .map(|(text, _)| display_width(text) + RIBBON_PADDING_COLS)
+                .sum()
+        };
+        // The last hints go first when they don't all fit
+        let help_width = self.content_width(cols) + DialogBox::MARGIN_COLS;
+        while ribbons.len() > 1 && ribbons_width(&ribbons) > help_width {
+            ribbons.pop();
+        }
+        let mut ribbon_x = cols.saturating_sub(ribbons_width(&ribbons)) / 2;
+        for (text, key_len) in ribbons {
+            let width = display_width(&text) + RIBBON_PADDING_COLS;
+            print_ribbon_with_coordinates(
+                Text::new(text).color_range(self.key_hint_color, 0..key_len),
+                ribbon_x,
+                rows - 1,
+                None,
+                None,
+            );
+            ribbon_x += width;
+        }
+    }
+}
+
+impl State {
+    /// Parse every configuration key into the plugin state
+    fn apply_configuration(&mut self, configuration: &BTreeMap<String, String>) {
+        self.action = configuration
+            .get("action")
+            .map(|action| Action::from_config(action))
+            .unwrap_or_default();
+        self.show_processes = parse_bool(configuration, "show_processes", false);
+        self.check_git = parse_bool(configuration, "check_git", false);
+        self.icons = parse_bool(configuration, "icons", false);
+        self.ascii_only = parse_bool(configuration, "ascii_only", false);
+        self.timeout = parse_duration(configuration, "timeout_seconds");
+        self.max_width = configuration
+            .get("max_width")
+            .and_then(|value| value.parse().ok())
+            .filter(|width| *width > 0);
+        self.result_duration = parse_duration(configuration, "result_seconds");
+        self.abort_window = parse_duration(configuration, "abort_window_seconds");
+        self.timeout_confirms = configuration
+            .get("timeout_action")
+            .is_some_and(|value| value == "confirm");
+        self.default_button = configuration
+            .get("default_button")
+            .and_then(|button| Button::from_config(button))
+            .unwrap_or_default();
+        self.border = configuration
+            .get("border")
+            .and_then(|border| BorderStyle::from_config(border))
+            .unwrap_or_default();
+        self.title_color = parse_color_index(configuration, "title_color", 2);
+        self.warning_color = parse_color_index(configuration, "warning_color", 3);
+        self.target_color = parse_color_index(configuration, "target_color", 1);
+        self.key_hint_color = parse_color_index(configuration, "key_hint_color", 2);
+        self.long_running_minutes = configuration
+            .get("long_running_minutes")
+            .and_then(|value| value.parse().ok());
+        self.close_plugin_panes = parse_bool(configuration, "close_plugin_panes", false);
+        self.minimal_permissions = parse_bool(configuration, "minimal_permissions", false);
+        self.follow_focus = parse_bool(configuration, "follow_focus", false);
+        self.show_help = parse_bool(configuration, "show_help", true);
+        self.help_text = configuration
+            .get("help_text")
+            .filter(|help_text| !help_text.is_empty())
+            .cloned();
+        self.session_name = configuration.get("session_name").cloned();
+        self.pipe_name = configuration.get("pipe_name").cloned();
+        self.broadcast_message = configuration.get("broadcast_message").cloned();
+        self.broadcast_payload = configuration
+            .get("broadcast_payload")
+            .cloned()
+            .unwrap_or_else(|| "{decision} {action}".to_string());
+        self.layout_file = configuration
+            .get("layout_file")
+            .cloned()
+            .unwrap_or_else(|| "session-layout.kdl".to_string());
+        // An empty `detach_key`/`float_key`/`stack_key`/`interrupt_key`/`disconnect_key` disables
+        // that alternative
+        self.confirm_keys = parse_keys(configuration, "confirm_key", &[BareKey::Char('y')]);
+        self.cancel_keys = parse_keys(
+            configuration,
+            "cancel_key",
+            &[BareKey::Esc, BareKey::Char('n')],
+        );
+        // e.g. `quit_confirm_key "Ctrl Enter"` while plain `confirm_key` covers the rest
+        self.action_confirm_keys = configuration
+            .iter()
+            .filter_map(|(key, keys)| {
+                let action = Action::parse_step(key.strip_suffix("_confirm_key")?)?;
+                Some((action, parse_key_list(keys)))
+            })
+            .filter(|(_, keys)| !keys.is_empty())
+            .collect();
+        self.vim_keys = parse_bool(configuration, "vim_keys", false);
+        self.double_confirm = parse_bool(configuration, "double_confirm", false);
+        self.any_key_cancels = parse_bool(configuration, "any_key_cancels", false);
+        let yn_shortcuts = parse_bool(configuration, "yn_shortcuts", false);
+        if yn_shortcuts {
+            // y and n on top of whatever keys were configured
+            for (keys, shortcut) in [(&mut self.confirm_keys, 'y'), (&mut self.cancel_keys, 'n')] {
+                let shortcut = KeyWithModifier::new(BareKey::Char(shortcut));
+                if !keys.contains(&shortcut) {
+                    keys.push(shortcut);
+                }
+            }
+        }
+        self.detach_key = parse_alternative_key(configuration, "detach_key", 'd');
+        self.float_key = parse_alternative_key(configuration, "float_key", 'f');
+        self.stack_key = parse_alternative_key(configuration, "stack_key", 's');
+        self.interrupt_key = parse_alternative_key(configuration, "interrupt_key", 'c');
+        self.disconnect_key = parse_alternative_key(configuration, "disconnect_key", 'o');
+        self.focus_after_close = configuration
+            .get("focus_after_close")
+            .and_then(|value| FocusAfterClose::from_config(value));
+        self.focus_tab_after_close = configuration
+            .get("focus_tab_after_close")
+            .and_then(|value| FocusTabAfterClose::from_config(value));
+        self.interrupt_tab_only = configuration
+            .get("interrupt_scope")
+            .is_some_and(|scope| scope == "tab");
+        self.last_tab_quits = parse_bool(configuration, "last_tab_quits", false);
+        self.danger_commands = configuration
+            .get("danger_commands")
+            .map(|value| parse_list(value))
+            .unwrap_or_default();
+        self.safe_commands = configuration
+            .get("safe_commands")
+            .map(|value| parse_list(value))
+            .unwrap_or_default();
+        self.rules = rules::rules_from_config(configuration);
+        self.unconfirmed_actions = configuration
+            .iter()
+            .filter(|(_, value)| config::parse_bool(value) == Some(false))
+            .filter_map(|(key, _)| Action::parse_step(key.strip_prefix("confirm_")?))
+            .collect();
+        self.strings = Strings::from_config(configuration);
+        self.confirmation_texts = configuration
+            .iter()
+            .filter_map(|(key, text)| {
+                Some((
+                    Action::parse_step(key.strip_suffix("_text")?)?,
+                    text.clone(),
+                ))
+            })
+            .collect();
+        self.skip_tabs = configuration
+            .get("skip_tabs")
+            .map(|value| parse_list(value))
+            .unwrap_or_default();
+        self.escalate_tabs = configuration
+            .get("escalate_tabs")
+            .and_then(|value| value.parse().ok());
+        self.escalate_panes = configuration
+            .get("escalate_panes")
+            .and_then(|value| value.parse().ok());
+        self.editor_typed_confirm = parse_bool(configuration, "editor_typed_confirm", false);
+        self.typed_confirm_word = configuration
+            .get("typed_confirm_word")
+            .filter(|word| !word.trim().is_empty())
+            .cloned();
+        self.skip_when_held = parse_bool(configuration, "skip_when_held", false);
+        self.skip_when_exited = parse_bool(configuration, "skip_when_exited", true);
+        self.skip_when_idle = parse_bool(configuration, "skip_when_idle", false);
+    }
+
+    /// Merge the plugin block's configuration over the `config_file`'s, if any, and parse it
+    fn load_configuration(&mut self) {
+        let mut configuration = self.block_configuration.clone();
+        self.config_problems = Vec::new();
+        self.config_file_path = None;
+        if let Some(config_file) = configuration.get("config_file") {
+            match config::host_path(config_file, &get_plugin_ids().initial_cwd) {
+                Ok(path) => self.config_file_path = Some(path),
+                Err(problem) => self.config_problems.push(problem),
+            }
+        }
+        if let Some(path) = &self.config_file_path {
+            let (mut file_configuration, problems) = config::read_file(path);
+            self.config_problems.extend(problems);
+            // The plugin block's own keys win over the shared file's
+            file_configuration.extend(configuration);
+            configuration = file_configuration;
+        }
+        self.config_problems
+            .extend(config::validate(&configuration));
+        self.apply_configuration(&configuration);
+        self.configuration = configuration;
+    }
+
+    /// Whether a path from a filesystem event is the `config_file`, matched by its components
+    /// below `/host` however the event spells the mount
+    fn is_config_file(&self, path: &std::path::Path) -> bool {
+        self.config_file_path
+            .as_deref()
+            .and_then(|config_file| config_file.strip_prefix("/host").ok())
+            .is_some_and(|relative| path.ends_with(relative))
+    }
+
+    /// Merge `key=value` pairs over the configuration and parse it again, like on load
+    fn reload_configuration(&mut self, pairs: BTreeMap<String, String>) {
+        self.block_configuration.extend(config::normalize(pairs));
+        self.load_configuration();
+        if self.config_file_path.is_some() {
+            watch_filesystem();
+        }
+        self.request_permissions();
+    }
+
+    /// Go back to the stored configuration after a dialog opened with overrides, keeping the
+    /// action the pipe asked for
+    fn restore_configuration(&mut self) {
+        if !self.configuration_overridden {
+            return;
+        }
+        self.configuration_overridden = false;
+        let action = self.action.clone();
+        let configuration = self.configuration.clone();
+        self.apply_configuration(&configuration);
+        self.action = action;
+    }
+
+    /// Whether a pipe message is meant for this instance: anything goes unless `pipe_name` is
+    /// configured, then the pipe's name or its `channel` argument has to match it
+    fn is_on_channel(&self, pipe_message: &PipeMessage) -> bool {
+        match &self.pipe_name {
+            Some(pipe_name) => {
+                pipe_message.name == *pipe_name
+                    || pipe_message.args.get("channel") == Some(pipe_name)
+            }
+            None => true,
+        }
+    }
+
+    /// Show the dialog for an action, about whatever is focused now
+    fn open_dialog(&mut self, request: DialogRequest) {
+        self.action = request.action;
+        self.request_permissions();
+        // Forget the previous targets and anything learned about them
+        self.target_pane_id = request.target_pane_id;
+        self.target_tab_index = None;
+        self.target_tab_pane_ids.clear();
+        self.pane_info_received = false;
+        self.answered = false;
+        self.seen_focus = None;
+        self.error = None;
+        self.quit_after_error = false;
+        self.result = None;
+        self.result_deadline = None;
+        self.pending_result = None;
+        self.confirm_armed = false;
+        self.armed_alternative = None;
+        self.focused_button = self.default_button;
+        self.hovered_button = None;
+        self.typed_input.clear();
+        self.target_processes.clear();
+        self.foreground_elapsed = None;
+        self.dirty_repos.clear();
+        self.idle_panes.clear();
+        self.idle_cursor = 0;
+        self.tab_pane_scroll = 0;
+        self.custom_title = request.title;
+        self.custom_message = request.message;
+        self.capture_targets();
+        show_self(true);
+        self.countdown_deadline = None;
+        self.countdown_tick_pending = false;
+        self.key_hint_deadline = None;
+        self.armed_deadline = None;
+        self.quit_deadline = None;
+        self.start_countdown();
+    }
+
+    /// Confirm the dialog, which takes a second confirmation when it is escalated
+    fn confirm(&mut self) {
+        if self.needs_second_press() && !self.confirm_armed {
+            // Escalated dialogs need the confirm key a second time, Enter included
+            self.confirm_armed = true;
+            self.armed_alternative = None;
+            self.focused_button = Button::Confirm;
+            if self.double_confirm {
+                self.armed_deadline =
+                    Some(Instant::now() + Duration::from_secs(DOUBLE_CONFIRM_SECONDS));
+                self.schedule_countdown_tick();
+            }
+        } else {
+            self.confirm_armed = false;
+            self.armed_alternative = None;
+            self.execute_action();
+        }
+    }
+
+    /// Whether the countdown running out confirms the dialog, which `timeout_action "confirm"`
+    /// asks for
+    ///
+    /// Nobody is there to give a second press or type the word, so dialogs asking for either are
+    /// cancelled instead.
+    fn countdown_confirms(&self) -> bool {
+        self.timeout_confirms && !self.needs_second_press() && !self.requires_typed_confirmation()
+    }
+
+    /// Whether confirming takes the confirm key twice, with `double_confirm` or because the
+    /// dialog is escalated
+    fn needs_second_press(&self) -> bool {
+        self.double_confirm || self.escalation_reason().is_some()
+    }
+
+    /// The characters to draw the dialog's border with
+    fn border_style(&self) -> BorderStyle {
+        if self.ascii_only && self.border != BorderStyle::None {
+            BorderStyle::Ascii
+        } else {
+            self.border
+        }
+    }
+
+    /// Arrow keys for the help line, spelled out with `ascii_only`
+    fn arrows(&self, arrows: &str, ascii: &str) -> String {
+        if self.ascii_only { ascii } else { arrows }.to_string()
+    }
+
+    /// Render one body line of the dialog, centered on row `y`
+    fn render_line(
+        &mut self,
+        line: &DialogLine,
+        dialog_box: &mut DialogBox,
+        y: usize,
+        cols: usize,
+    ) {
+        let text = match line {
+            DialogLine::Blank => return,
+            DialogLine::Buttons => return self.render_buttons(dialog_box, y, cols),
+            DialogLine::Input => return self.render_input(dialog_box, y, cols),
+            DialogLine::Text(text)
+            | DialogLine::Prefixed(text, _, _)
+            | DialogLine::Danger(text)
+            | DialogLine::DangerBlock(text) => &truncate_to_width(text, self.content_width(cols)),
+        };
+        let text_x = cols.saturating_sub(display_width(text)) / 2;
+        dialog_box.include(display_width(text), y);
+        match line {
+            DialogLine::Prefixed(_, color, prefix_len) => print_text_with_coordinates(
+                Text::new(text).color_range(*color, 0..*prefix_len),
+                text_x,
+                y,
+                None,
+                None,
+            ),
+            DialogLine::Danger(_) => print_danger_text(text, text_x, y, self.styling.as_ref()),
+            DialogLine::DangerBlock(_) => {
+                print_danger_block(text, text_x, y, self.styling.as_ref())
+            }
+            _ => print_text_with_coordinates(Text::new(text), text_x, y, None, None),
+        }
+    }
+
+    /// The columns lines inside the border may take, at most `max_width`
+    fn content_width(&self, cols: usize) -> usize {
+        let width = cols.saturating_sub(DialogBox::MARGIN_COLS);
+        self.max_width
+            .map_or(width, |max_width| width.min(max_width))
+    }
+
+    /// Render the whole dialog as a single line, for panes too short for the regular layout
+    fn render_compact(&self, title: &str, dangerous: bool, rows: usize, cols: usize) {
+        let confirm_label = self.confirm_key_label();
+        let cancel_label = self.cancel_key_label();
+        let keys = if self.requires_typed_confirmation() {
+            self.strings.format(
+                "compact.typed",
+                &[
+                    ("word", &self.confirmation_word()),
+                    ("input", &self.typed_input),
+                    // Every other key is typed into the word
+                    ("cancel", "Esc"),
+                ],
+            )
+        } else if let Some((alternative, key)) = self.armed_alternative_key() {
+            self.strings.format(
+                "compact.armed_alternative",
+                &[
+                    ("key", &key),
+                    ("alternative", self.strings.get(alternative.hint_id())),
+                    ("cancel", &cancel_label),
+                ],
+            )
+        } else {
+            let id = if self.confirm_armed {
+                "compact.armed"
+            } else {
+                "compact"
+            };
+            self.strings.format(
+                id,
+                &[("confirm", &confirm_label), ("cancel", &cancel_label)],
+            )
+        };
+        let title = truncate_to_width(&format!("<{}>", title), cols);
+        let title_width = display_width(&title);
+        let keys = truncate_to_width(&format!(" {}", keys), cols - title_width);
+        let x = cols.saturating_sub(title_width + display_width(&keys)) / 2;
+        let y = rows / 2;
+        if dangerous {
+            print_danger_text(&title, x, y, self.styling.as_ref());
+            print_text_with_coordinates(Text::new(keys), x + title_width, y, None, None);
+        } else {
+            let line = format!("{}{}", title, keys);
+            print_text_with_coordinates(
+                Text::new(&line).color_range(self.title_color, 0..title.chars().count()),
+                x,
+                y,
+                None,
+                None,
+            );
+        }
+    }
+
+    /// Activate a button, by Enter or a click
+    fn press(&mut self, button: Button) {
+        match button {
+            Button::Confirm => self.confirm(),
+            Button::Cancel => {
+                self.confirm_armed = false;
+                self.armed_alternative = None;
+                self.cancel();
+            }
+        }
+    }
+
+    /// The button rendered at the given mouse position, if any
+    fn button_at(&self, line: isize, column: usize) -> Option<Button> {
+        let line = usize::try_from(line).ok()?;
+        self.button_positions
+            .iter()
+            .find(|(_, y, columns)| *y == line && columns.contains(&column))
+            .map(|(button, _, _)| *button)
+    }
+
+    /// Render the Confirm and Cancel buttons centered on row `y`, the selected one highlighted
+    /// and the hovered one colored
+    fn render_buttons(&mut self, dialog_box: &mut DialogBox, y: usize, cols: usize) {
+        let gap = 3;
+        let labels = [Button::Confirm, Button::Cancel].map(|button| {
+            let label = button.label(&self.strings);
+            (button, label)
+        });
+        let width = display_width(&labels[0].1) + gap + display_width(&labels[1].1);
+        dialog_box.include(width, y);
+        let mut x = cols.saturating_sub(width) / 2;
+        for (button, label) in labels {
+            let label_width = display_width(&label);
+            let mut text = Text::new(&label);
+            if button == self.focused_button {
+                text = text.selected();
+            }
+            if self.hovered_button == Some(button) {
+                text = text.color_range(2, 0..label.chars().count());
+            }
+            print_text_with_coordinates(text, x, y, None, None);
+            self.button_positions.push((button, y, x..x + label_width));
+            x += label_width + gap;
+        }
+    }
+
+    /// Render the typed confirmation so far as a highlighted field wide enough for the word
+    fn render_input(&self, dialog_box: &mut DialogBox, y: usize, cols: usize) {
+        let input = format!("{}_", self.typed_input);
+        let field_width = display_width(&self.confirmation_word()).max(display_width(&input)) + 1;
+        let field = format!(
+            " {}{}",
+            input,
+            " ".repeat(field_width - display_width(&input))
+        );
+        let field = truncate_to_width(&field, self.content_width(cols));
+        let field_x = cols.saturating_sub(display_width(&field)) / 2;
+        dialog_box.include(display_width(&field), y);
+        print_text_with_coordinates(Text::new(field).selected(), field_x, y, None, None);
+    }
+
+    /// Render the target pane's processes from row `y` on
+    fn render_processes(&self, dialog_box: &mut DialogBox, y: usize, max_rows: usize, cols: usize) {
+        let processes = self.target_processes.iter().take(max_rows);
+        for (index, process) in processes.enumerate() {
+            let process = truncate_to_width(process, self.content_width(cols));
+            let process_x = cols.saturating_sub(display_width(&process)) / 2;
+            dialog_box.include(display_width(&process), y + index);
+            print_text_with_coordinates(Text::new(process), process_x, y + index, None, None);
+        }
+    }
+
+    /// Render the panes of the target tab from row `y` on, scrolled by `tab_pane_scroll`
+    fn render_tab_panes(
+        &mut self,
+        dialog_box: &mut DialogBox,
+        y: usize,
+        max_rows: usize,
+        cols: usize,
+    ) {
+        let entries = self.target_tab_pane_entries();
+        self.tab_pane_rows = max_rows;
+        self.tab_pane_scroll = self
+            .tab_pane_scroll
+            .min(entries.len().saturating_sub(max_rows));
+        let visible = entries.iter().skip(self.tab_pane_scroll).take(max_rows);
+        for (row, entry) in visible.enumerate() {
+            let entry = truncate_to_width(entry, self.content_width(cols));
+            let entry_x = cols.saturating_sub(display_width(&entry)) / 2;
+            dialog_box.include(display_width(&entry), y + row);
+            print_text_with_coordinates(Text::new(entry), entry_x, y + row, None, None);
+        }
+    }
+
+    /// Render a table of the session's tabs and their pane counts from row `y` on, for the
+    /// dialogs ending the session
+    fn render_tab_table(&self, dialog_box: &mut DialogBox, y: usize, max_rows: usize, cols: usize) {
+        let mut rows = vec![[
+            self.strings.get("tab_table.tab").to_string(),
+            self.strings.get("tab_table.panes").to_string(),
+        ]];
+        for tab in &self.tabs {
+            rows.push([
+                format!("#{} {}", tab.position + 1, tab.name),
+                self.tab_pane_count(tab.position).to_string(),
+            ]);
+        }
+        if rows.len() > max_rows && max_rows > 1 {
+            // The last row that fits tells how many tabs didn't
+            let hidden = rows.len() - max_rows + 1;
+            rows.truncate(max_rows - 1);
+            let hidden = hidden.to_string();
+            rows.push([
+                self.strings.format("more", &[("count", &hidden)]),
+                String::new(),
+            ]);
+        }
+        rows.truncate(max_rows);
+        if rows.is_empty() {
+            return;
+        }
+        let max_name_width = self.content_width(cols).saturating_sub(6);
+        for row in &mut rows {
+            row[0] = truncate_to_width(&row[0], max_name_width);
+        }
+        let column_width = |column: usize| {
+            rows.iter()
+                .map(|row| display_width(&row[column]))
+                .max()
+                .unwrap_or(0)
+        };
+        // Columns are separated by a space
+        let width = column_width(0) + 1 + column_width(1);
+        let table = rows
+            .iter()
+            .fold(Table::new(), |table, row| table.add_row(row.to_vec()));
+        let x = cols.saturating_sub(width) / 2;
+        dialog_box.include(width, y + rows.len() - 1);
+        print_table_with_coordinates(table, x, y, None, Some(rows.len()));
+    }
+
+    /// Render the idle panes for CloseIdlePanes, scrolled to keep the highlighted one in view
+    fn render_idle_list(&self, dialog_box: &mut DialogBox, y: usize, max_rows: usize, cols: usize) {
+        let first = (self.idle_cursor + 1).saturating_sub(max_rows);
+        let visible = self
+            .idle_panes
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(max_rows);
+        for (row, (index, (pane_id, selected))) in visible.enumerate() {
+            let title = self
+                .pane(pane_id)
+                .map(|pane| pane.title.as_str())
+                .unwrap_or_default();
+            let mut entry = format!(
+                "[{}] #{} {}",
+                if *selected { 'x' } else { ' ' },
+                pane_id_number(pane_id),
+                title
+            );
+            if let Some(tab_name) = self.pane_tab_name(pane_id) {
+                entry.push_str(&format!(" ({})", tab_name));
+            }
+            let entry = truncate_to_width(&entry, self.content_width(cols));
+            let entry_x = cols.saturating_sub(display_width(&entry)) / 2;
+            dialog_box.include(display_width(&entry), y + row);
+            let mut text = Text::new(entry);
+            if index == self.idle_cursor {
+                text = text.selected();
+            }
+            print_text_with_coordinates(text, entry_x, y + row, None, None);
+        }
+    }
+
+    /// Whether the action is told about the session's tabs, panes and clients, which
+    /// `minimal_permissions` goes without when quitting
+    fn reads_session_state(&self) -> bool {
+        !(self.minimal_permissions && self.action == Action::QuitSession)
+    }
+
+    /// Ask for the permissions the action needs, and subscribe to the session's state when it
+    /// reads it
+    ///
+    /// With `minimal_permissions` quitting asks for nothing but ending the session, and the
+    /// session's tabs, panes and clients are never subscribed to.
+    fn request_permissions(&self) {
+        let messages_plugins = self.broadcast_message.is_some() || self.answers_plugins;
+        if !self.reads_session_state() {
+            let mut permissions = vec![PermissionType::ChangeApplicationState];
+            if messages_plugins {
+                permissions.push(PermissionType::MessageAndLaunchOtherPlugins);
+            }
+            request_permission(&permissions);
+            return;
+        }
+        subscribe(&[
+            EventType::PaneUpdate,
+            EventType::TabUpdate,
+            EventType::SessionUpdate,
+            EventType::ModeUpdate,
+        ]);
+        let mut permissions = vec![
+            PermissionType::ChangeApplicationState,
+            PermissionType::ReadApplicationState,
+            // What runs in the panes is found in `/proc` through a shell, and deleting the
+            // running session goes through the zellij CLI and worktrees through git
+            PermissionType::RunCommands,
+        ];
+        if messages_plugins {
+            // Broadcasting decisions, or answering `confirm` messages from other plugins
+            permissions.push(PermissionType::MessageAndLaunchOtherPlugins);
+        }
+        request_permission(&permissions);
+    }
+
+    /// With `follow_focus`, make the pane and tab focused since the last update the dialog's
+    /// target, e.g. after switching tabs from another client
+    fn follow_focus(&mut self) {
+        if !self.follow_focus || self.answered || !self.pane_info_received || !self.shows_dialog() {
+            return;
+        }
+        let Some(tab_index) = get_focused_tab(&self.tabs).map(|tab| tab.position) else {
+            return;
+        };
+        let pane_id = get_focused_pane(tab_index, &self.panes).map(|pane| pane_id_of(&pane));
+        let focus = Some((tab_index, pane_id));
+        if self.seen_focus == focus {
+            return;
+        }
+        let first_update = self.seen_focus.is_none();
+        self.seen_focus = focus;
+        if first_update {
+            // What was focused when the dialog opened is its target already
+            return;
+        }
+        self.target_tab_index = Some(tab_index);
+        // Taken again from the next update, which has the new tab's panes
+        self.target_tab_pane_ids.clear();
+        if pane_id.is_some() {
+            self.target_pane_id = pane_id;
+        }
+        // Anything learned about the previous target, and a press meant for it, is forgotten
+        self.confirm_armed = false;
+        self.armed_alternative = None;
+        self.tab_pane_scroll = 0;
+        self.target_processes.clear();
+        self.foreground_elapsed = None;
+        self.dirty_repos.clear();
+        self.list_target_processes();
+        self.check_git_status();
+    }
+
+    /// Cancel the open dialog once what it asks about was closed some other way, rather than
+    /// leave it up to act on nothing or on whatever took its place
+    fn dismiss_if_target_gone(&mut self) {
+        if self.answered || !self.shows_dialog() {
+            return;
+        }
+        let pane_gone = self.dialog_action().targets_pane()
+            && self
+                .target_pane_id
+                .is_some_and(|pane_id| self.pane(&pane_id).is_none());
+        // Found again wherever it moved by now, so this is a tab that's really gone
+        let tab_gone = self.dialog_action().steps().contains(&Action::CloseTab)
+            && self
+                .target_tab_index
+                .is_some_and(|position| !self.is_target_tab_at(position));
+        if pane_gone || tab_gone {
+            self.cancel();
+        }
+    }
+
+    /// Follow the target tab to its current position, which shifts when tabs before it are
+    /// created, closed or moved
+    fn resolve_target_tab(&mut self) {
+        let Some(position) = self.target_tab_index else {
+            return;
+        };
+        if self.is_target_tab_at(position) {
+            return;
+        }
+        if let Some(position) = self
+            .panes
+            .panes
+            .keys()
+            .copied()
+            .filter(|position| self.is_target_tab_at(*position))
+            .min()
+        {
+            self.target_tab_index = Some(position);
+        }
+    }
+
+    /// Whether the tab at `position` is the target tab, holding one of the panes it had when it
+    /// was captured
+    fn is_target_tab_at(&self, position: usize) -> bool {
+        self.target_tab_pane_ids.is_empty()
+            || self
+                .tab_panes(position)
+                .into_iter()
+                .any(|pane| self.target_tab_pane_ids.contains(&pane_id_of(pane)))
+    }
+
+    /// Capture the focused tab and pane from the latest updates, once per dialog
+    fn capture_targets(&mut self) {
+        if !self.reads_session_state() {
+            // No updates are coming, so there is nothing to wait for
+            if !self.pane_info_received {
+                self.pane_info_received = true;
+                if self.is_pass_through() {
+                    self.execute_action();
+                }
+            }
+            return;
+        }
+        if self.target_tab_index.is_none() {
+            // A pane requested through the pipe brings its own tab
+            self.target_tab_index = match &self.target_pane_id {
+                Some(pane_id) => self.pane_tab_position(pane_id),
+                None => get_focused_tab(&self.tabs).map(|focused_tab| focused_tab.position),
+            };
+        }
+        if self.target_tab_pane_ids.is_empty() {
+            if let Some(tab_index) = self.target_tab_index {
+                self.target_tab_pane_ids = self
+                    .tab_panes(tab_index)
+                    .into_iter()
+                    .map(pane_id_of)
+                    .collect();
+            }
+        }
+        if !self.pane_info_received {
+            // Find the focused non-plugin pane in the target tab
+            if let Some(tab_index) = self.target_tab_index {
+                self.pane_info_received = true;
+                self.scan_idle_panes();
+                if self.is_pass_through() {
+                    self.capture_target_pane(tab_index);
+                    self.execute_action();
+                    return;
+                }
+                let pane_info = self.capture_target_pane(tab_index);
+                self.list_target_processes();
+                self.check_git_status();
+                if let Some(pane_info) = pane_info {
+                    let finished = (self.skip_when_exited && pane_info.exited)
+                        || (self.skip_when_held && pane_info.is_held);
+                    if finished && self.action == Action::ClosePane {
+                        // Nothing left to lose in a command pane whose command is done
+                        self.execute_action();
+                        return;
+                    }
+                }
+                // Whether to skip the dialog may depend on the target's foreground command,
+                // which comes with the scan of the panes' commands. Without the permission to
+                // scan, or if the scan doesn't answer in time, the dialog is shown as usual.
+                self.skip_check_pending = !self.permissions_denied && self.may_skip_dialog();
+                if self.skip_check_pending {
+                    self.skip_check_deadline =
+                        Some(Instant::now() + Duration::from_secs(SKIP_CHECK_SECONDS));
+                    self.schedule_countdown_tick();
+                }
+                self.scan_pane_commands();
+            }
+        }
+    }
+
+    /// Capture the focused terminal pane of the given tab as the target, unless the pipe named
+    /// the target already
+    fn capture_target_pane(&mut self, tab_index: usize) -> Option<PaneInfo> {
+        if let Some(pane_id) = &self.target_pane_id {
+            return self.pane(pane_id).cloned();
+        }
+        let pane_info = get_focused_pane(tab_index, &self.panes)?;
+        self.target_pane_id = Some(pane_id_of(&pane_info));
+        Some(pane_info)
+    }
+
+    /// Whether the dialog needs to know what runs in the target pane beyond its command line
+    fn inspects_processes(&self) -> bool {
+        self.show_processes || self.long_running_minutes.is_some()
+    }
+
+    /// Ask for the process tree of the target pane, for `show_processes` and
+    /// `long_running_minutes`
+    fn list_target_processes(&self) {
+        if !self.inspects_processes() || !self.action.targets_pane() {
+            return;
+        }
+        let Some(PaneId::Terminal(pane_id)) = self.target_pane_id else {
+            return;
+        };
+        let zellij_pid = get_plugin_ids().zellij_pid.to_string();
+        let pane_id = pane_id.to_string();
+        let context = BTreeMap::from([(processes::WORKER_NAME.to_string(), pane_id.clone())]);
+        run_command(
+            &[
+                "sh",
+                "-c",
+                processes::LIST_SCRIPT,
+                "sh",
+                &zellij_pid,
+                &pane_id,
+            ],
+            context,
+        );
+    }
+
+    /// Ask which worktrees the affected panes are in have uncommitted changes, if `check_git`
+    /// is on
+    fn check_git_status(&self) {
+        if !self.check_git {
+            return;
+        }
+        let pane_ids: Vec<String> = self
+            .affected_terminal_pane_ids()
+            .iter()
+            .map(|pane_id| pane_id_number(pane_id).to_string())
+            .collect();
+        if pane_ids.is_empty() {
+            return;
+        }
+        let zellij_pid = get_plugin_ids().zellij_pid.to_string();
+        let mut command = vec!["sh", "-c", processes::GIT_STATUS_SCRIPT, "sh", &zellij_pid];
+        command.extend(pane_ids.iter().map(String::as_str));
+        let context = BTreeMap::from([(processes::GIT_STATUS_CONTEXT.to_string(), String::new())]);
+        run_command(&command, context);
+    }
+
+    /// Look for panes with an idle shell across the session, for CloseIdlePanes
+    fn scan_idle_panes(&mut self) {
+        if !self.action.steps().contains(&Action::CloseIdlePanes) {
+            return;
+        }
+        self.idle_scan_pending = true;
+        let zellij_pid = get_plugin_ids().zellij_pid.to_string();
+        let context = BTreeMap::from([(processes::IDLE_CONTEXT.to_string(), String::new())]);
+        run_command(
+            &["sh", "-c", processes::PANES_SCRIPT, "sh", &zellij_pid],
+            context,
+        );
+    }
+
+    /// Find the command in the foreground of every terminal pane, which commands, rules and
+    /// warnings about the panes go by
+    fn scan_pane_commands(&self) {
+        let zellij_pid = get_plugin_ids().zellij_pid.to_string();
+        let context = BTreeMap::from([(processes::COMMANDS_CONTEXT.to_string(), String::new())]);
+        run_command(
+            &["sh", "-c", processes::PANES_SCRIPT, "sh", &zellij_pid],
+            context,
+        );
+    }
+
+    /// Whether the key moves through or toggles the list of idle panes
+    fn is_idle_list_key(&self, key: &KeyWithModifier) -> bool {
+        !self.idle_panes.is_empty()
+            && self
+                .dialog_action()
+                .steps()
+                .contains(&Action::CloseIdlePanes)
+            && matches!(
+                key.bare_key,
+                BareKey::Up
+                    | BareKey::Down
+                    | BareKey::Char('k')
+                    | BareKey::Char('j')
+                    | BareKey::Char(' ')
+            )
+    }
+
+    fn handle_idle_list_key(&mut self, key: KeyWithModifier) {
+        let last = self.idle_panes.len() - 1;
+        match key.bare_key {
+            BareKey::Up | BareKey::Char('k') => {
+                self.idle_cursor = self.idle_cursor.saturating_sub(1)
+            }
+            BareKey::Down | BareKey::Char('j') => {
+                self.idle_cursor = (self.idle_cursor + 1).min(last)
+            }
+            _ => {
+                if let Some((_, selected)) = self.idle_panes.get_mut(self.idle_cursor) {
+                    *selected = !*selected;
+                }
+            }
+        }
+    }
+
+    /// The ids of the idle panes selected for closing
+    fn selected_idle_pane_ids(&self) -> Vec<PaneId> {
+        self.idle_panes
+            .iter()
+            .filter(|(_, selected)| *selected)
+            .map(|(pane_id, _)| *pane_id)
+            .collect()
+    }
+
+    /// The name of the tab a pane is in
+    fn pane_tab_name(&self, pane_id: &PaneId) -> Option<&str> {
+        let tab_position = self.pane_tab_position(pane_id)?;
+        self.tabs
+            .iter()
+            .find(|tab| tab.position == tab_position)
+            .map(|tab| tab.name.as_str())
+    }
+
+    /// The position of the tab a pane is in
+    fn pane_tab_position(&self, pane_id: &PaneId) -> Option<usize> {
+        self.panes
+            .panes
+            .iter()
+            .find(|(_, panes)| panes.iter().any(|pane| pane_id_of(pane) == *pane_id))
+            .map(|(tab_position, _)| *tab_position)
+    }
+
+    /// Whether every step of the action was configured to run without confirmation
+    fn is_pass_through(&self) -> bool {
+        self.action
+            .steps()
+            .iter()
+            .all(|step| self.unconfirmed_actions.contains(step))
+    }
+
+    /// The latest info about the target tab
+    fn target_tab(&self) -> Option<&TabInfo> {
+        let tab_index = self.target_tab_index?;
+        self.tabs.iter().find(|tab| tab.position == tab_index)
+    }
+
+    /// The first configured rule matching the current target
+    fn matching_rule(&self) -> Option<&Rule> {
+        let target = RuleTarget {
+            action: &self.action,
+            command: self.target_command(),
+            tab_name: self.target_tab().map(|tab| tab.name.as_str()),
+        };
+        rules::first_match(&self.rules, &target)
+    }
+
+    /// Whether anything is configured that could skip the dialog for this action
+    fn may_skip_dialog(&self) -> bool {
+        let pane_skip =
+            self.action.targets_pane() && (self.skip_when_idle || !self.safe_commands.is_empty());
+        let tab_skip = matches!(self.action, Action::ClosePane | Action::CloseTab)
+            && !self.skip_tabs.is_empty();
+        pane_skip
+            || tab_skip
+            || self
+                .rules
+                .iter()
+                .any(|rule| rule.outcome == RuleOutcome::Skip)
+    }
+
+    /// Whether the action should run right away, a matching rule takes precedence over the
+    /// `skip_tabs`/`skip_when_idle`/`safe_commands` options
+    fn should_skip_dialog(&self) -> bool {
+        match self.matching_rule() {
+            Some(rule) => rule.outcome == RuleOutcome::Skip,
+            None => {
+                self.target_in_skip_tab() || (self.action.targets_pane() && self.can_skip_dialog())
+            }
+        }
+    }
+
+    /// Whether a pane or tab is being closed in a tab matching `skip_tabs`
+    fn target_in_skip_tab(&self) -> bool {
+        matches!(self.action, Action::ClosePane | Action::CloseTab)
+            && self.target_tab().is_some_and(|tab| {
+                self.skip_tabs
+                    .iter()
+                    .any(|pattern| rules::glob_match(pattern, &tab.name))
+            })
+    }
+
+    /// The command running in the target pane: its foreground process as found in `/proc`,
+    /// otherwise the command of a command pane
+    fn target_command(&self) -> Option<&str> {
+        self.pane_command(&self.target_pane_id?)
+    }
+
+    /// The foreground command of a pane as found in `/proc`, or else the command of a command
+    /// pane
+    fn pane_command(&self, pane_id: &PaneId) -> Option<&str> {
+        self.running_commands
+            .get(pane_id)
+            .map(String::as_str)
+            .or_else(|| self.pane(pane_id)?.terminal_command.as_deref())
+            .filter(|command| !command.trim().is_empty())
+    }
+
+    /// Whether the target pane runs nothing worth a confirmation: just its shell (with
+    /// `skip_when_idle`) or one of the `safe_commands`
+    fn can_skip_dialog(&self) -> bool {
+        if self.target_pane().is_none_or(|pane| pane.is_plugin) {
+            return false;
+        }
+        let Some(command) = self.target_command() else {
+            return false;
+        };
+        // A shell with background jobs still has something to lose
+        let idle = is_shell(command)
+            && self
+                .target_pane_id
+                .and_then(|pane_id| self.child_process_counts.get(&pane_id))
+                .is_some_and(|children| *children == 0);
+        (self.skip_when_idle && idle)
+            || self
+                .safe_commands
+                .iter()
+                .any(|safe_command| safe_command == command_name(command))
+    }
+
+    /// Why the dialog needs an escalated confirmation, if it does
+    fn escalation_reason(&self) -> Option<String> {
+        // A matching rule takes precedence over the `danger_commands`/`escalate_*` options
+        if let Some(rule) = self.matching_rule() {
+            return (rule.outcome == RuleOutcome::Escalate).then(|| {
+                self.strings
+                    .format("escalation.rule", &[("rule", &rule.name)])
+            });
+        }
+        self.danger_command_reason()
+            .or_else(|| self.long_running_reason())
+            .or_else(|| self.large_session_reason())
+    }
+
+    /// Extra warning lines for the dialog, below the target info
+    fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.is_closing_last_tab() {
+            warnings.push(self.strings.get("warning.last_tab").to_string());
+        }
+        if let Some(editor) = self.target_editor() {
+            warnings.push(self.strings.format("warning.editor", &[("editor", editor)]));
+        }
+        for repo in &self.dirty_repos {
+            warnings.push(
+                self.strings
+                    .format("warning.uncommitted", &[("repo", repo)]),
+            );
+        }
+        let other_clients = self.other_client_count();
+        if other_clients > 0 && self.ends_session() {
+            warnings.push(
+                self.strings
+                    .format_count("warning.other_clients", other_clients, &[]),
+            );
+        }
+        warnings
+    }
+
+    /// The `ssh`/`mosh` commands running in the panes the action takes down
+    fn remote_connections(&self) -> Vec<&str> {
+        self.affected_terminal_pane_ids()
+            .iter()
+            .filter_map(|pane_id| self.pane_command(pane_id))
+            .filter(|command| is_remote_connection(command))
+            .map(str::trim)
+            .collect()
+    }
+
+    /// How many clients besides this one are attached to the current session
+    fn other_client_count(&self) -> usize {
+        self.current_session()
+            .map(|session| session.connected_clients.saturating_sub(1))
+            .unwrap_or_default()
+    }
+
+    /// Whether the dialog's action ends the current session for every attached client
+    fn ends_session(&self) -> bool {
+        self.dialog_action().steps().iter().any(|step| {
+            matches!(
+                step,
+                Action::QuitSession | Action::KillAndDeleteSession | Action::KillAllSessions
+            )
+        })
+    }
+
+    /// The editor running in the target pane of a pane action, if any
+    fn target_editor(&self) -> Option<&str> {
+        if !self.action.targets_pane() {
+            return None;
+        }
+        let command = self.target_command()?;
+        is_editor(command).then(|| command_name(command))
+    }
+
+    /// Whether the dialog needs the confirmation word typed instead of a single key
+    fn requires_typed_confirmation(&self) -> bool {
+        self.typed_confirm_word.is_some()
+            || self
+                .matching_rule()
+                .is_some_and(|rule| rule.outcome == RuleOutcome::TypedConfirm)
+            || (self.editor_typed_confirm && self.target_editor().is_some())
+    }
+
+    /// The word to type out, the configured `typed_confirm_word` with its placeholders filled in,
+    /// e.g. `{session_name}`, or `yes`
+    fn confirmation_word(&self) -> String {
+        match &self.typed_confirm_word {
+            Some(word) => self.fill_placeholders(word.trim()),
+            None => CONFIRMATION_WORD.to_string(),
+        }
+    }
+
+    /// Edit the typed confirmation, confirming on Enter once the word matches
+    fn handle_typed_key(&mut self, key: KeyWithModifier) {
+        match key.bare_key {
+            BareKey::Enter if self.typed_input == self.confirmation_word() => {
+                self.typed_input.clear();
+                self.execute_action();
+            }
+            // A wrong word starts over rather than confirming
+            BareKey::Enter => self.typed_input.clear(),
+            BareKey::Backspace => {
+                self.typed_input.pop();
+            }
+            BareKey::Char(c) if key.has_no_modifiers() => self.typed_input.push(c),
+            _ => {
+                self.typed_input.clear();
+                self.cancel();
+            }
+        }
+    }
+
+    /// Escalate pane actions when the target runs one of the `danger_commands`
+    fn danger_command_reason(&self) -> Option<String> {
+        if !self.action.targets_pane() {
+            return None;
+        }
+        let command = self.target_command()?;
+        let name = command_name(command);
+        self.danger_commands
+            .iter()
+            .any(|danger_command| danger_command == name)
+            .then(|| {
+                self.strings
+                    .format("escalation.danger_command", &[("command", name)])
+            })
+    }
+
+    /// Escalate pane actions when the foreground command runs for longer than
+    /// `long_running_minutes`
+    fn long_running_reason(&self) -> Option<String> {
+        let limit_secs = self.long_running_minutes? * 60;
+        let (elapsed, command) = self.foreground_elapsed.as_ref()?;
+        (*elapsed > limit_secs).then(|| {
+            self.strings.format(
+                "escalation.long_running",
+                &[
+                    ("command", command_name(command)),
+                    ("duration", &format_duration(*elapsed)),
+                ],
+            )
+        })
+    }
+
+    /// Escalate quitting a session with more tabs or panes than the configured thresholds
+    fn large_session_reason(&self) -> Option<String> {
+        if self.dialog_action() != Action::QuitSession {
+            return None;
+        }
+        let tab_count = self.tabs.len();
+        let pane_count = self.session_pane_count();
+        let too_many_tabs = self.escalate_tabs.is_some_and(|limit| tab_count > limit);
+        let too_many_panes = self.escalate_panes.is_some_and(|limit| pane_count > limit);
+        (too_many_tabs || too_many_panes).then(|| {
+            self.strings.format(
+                "escalation.large_session",
+                &[
+                    (
+                        "tabs",
+                        &self.strings.format_count("count.tab", tab_count, &[]),
+                    ),
+                    (
+                        "panes",
+                        &self.strings.format_count("count.pane", pane_count, &[]),
+                    ),
+                ],
+            )
+        })
+    }
+
+    /// Which list the dialog shows below its body
+    fn dialog_list(&self, action: &Action) -> DialogList {
+        let steps = action.steps();
+        if steps.contains(&Action::CloseIdlePanes) {
+            DialogList::IdlePanes
+        } else if steps.contains(&Action::QuitSession) && !self.tabs.is_empty() {
+            DialogList::SessionTabs
+        } else if steps.contains(&Action::CloseTab) {
+            DialogList::TabPanes
+        } else if self.show_processes {
+            DialogList::Processes
+        } else {
+            DialogList::None
+        }
+    }
+
+    /// The panes of the tab at `position`, not counting UI bars and this plugin
+    fn tab_panes(&self, position: usize) -> Vec<&PaneInfo> {
+        self.panes
+            .panes
+            .get(&position)
+            .map(|panes| {
+                panes
+                    .iter()
+                    .filter(|pane| pane.is_selectable)
+                    .filter(|pane| !(pane.is_plugin && pane.id == self.plugin_id))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// How many panes the tab at `position` has, not counting UI bars and this plugin
+    fn tab_pane_count(&self, position: usize) -> usize {
+        self.tab_panes(position).len()
+    }
+
+    /// One line per pane of the target tab, with its title and what it runs
+    fn target_tab_pane_entries(&self) -> Vec<String> {
+        let Some(tab_index) = self.target_tab_index else {
+            return Vec::new();
+        };
+        self.tab_panes(tab_index)
+            .into_iter()
+            .map(|pane| {
+                let pane_id = pane_id_of(pane);
+                let entry = format!("#{} {}", pane.id, pane.title);
+                if pane.is_plugin {
+                    self.strings
+                        .format("tab_table.plugin", &[("entry", &entry)])
+                } else {
+                    match self.pane_command(&pane_id) {
+                        Some(command) if command != pane.title => {
+                            format!("{} ({})", entry, command)
+                        }
+                        _ => entry,
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Whether the key scrolls the target tab's pane list, which only happens when it doesn't fit
+    fn is_tab_pane_scroll_key(&self, key: &KeyWithModifier) -> bool {
+        self.dialog_list(&self.dialog_action()) == DialogList::TabPanes
+            && self.target_tab_pane_entries().len() > self.tab_pane_rows
+            && matches!(
+                key.bare_key,
+                BareKey::Up | BareKey::Down | BareKey::Char('k') | BareKey::Char('j')
+            )
+    }
+
+    fn handle_tab_pane_scroll_key(&mut self, key: KeyWithModifier) {
+        let last_first = self
+            .target_tab_pane_entries()
+            .len()
+            .saturating_sub(self.tab_pane_rows);
+        match key.bare_key {
+            BareKey::Up | BareKey::Char('k') => {
+                self.tab_pane_scroll = self.tab_pane_scroll.saturating_sub(1)
+            }
+            _ => self.tab_pane_scroll = (self.tab_pane_scroll + 1).min(last_first),
+        }
+    }
+
+    /// How many panes the session has, not counting UI bars and this plugin
+    fn session_pane_count(&self) -> usize {
+        self.panes
+            .panes
+            .values()
+            .flatten()
+            .filter(|pane| pane.is_selectable)
+            .filter(|pane| !(pane.is_plugin && pane.id == self.plugin_id))
+            .count()
+    }
+
+    /// Whether the CloseTab action targets the only tab left, so it would end the session
+    fn is_closing_last_tab(&self) -> bool {
+        self.action == Action::CloseTab && self.target_tab_index.is_some() && self.tabs.len() == 1
+    }
+
+    /// The action the dialog confirms, which is the configured one unless closing
+    /// the last tab escalates it to quitting the session
+    fn dialog_action(&self) -> Action {
+        if self.last_tab_quits && self.is_closing_last_tab() {
+            Action::QuitSession
+        } else {
+            self.action.clone()
+        }
+    }
+
+    /// The alternatives offered by the current dialog, with the key that picks each
+    fn alternatives(&self) -> Vec<(Alternative, &KeyWithModifier)> {
+        let mut offered = Vec::new();
+        match self.dialog_action() {
+            Action::QuitSession => {
+                offered.push((Alternative::Detach, &self.detach_key));
+                if self.other_client_count() > 0 {
+                    offered.push((Alternative::DisconnectOthers, &self.disconnect_key));
+                }
+            }
+            Action::ClosePane => {
+                let target_pane = self.target_pane();
+                if target_pane.is_some_and(|pane| !pane.is_floating) {
+                    offered.push((Alternative::Float, &self.float_key));
+                }
+                if target_pane.is_some_and(|pane| !pane.is_plugin) {
+                    offered.push((Alternative::Interrupt, &self.interrupt_key));
+                }
+                if !self.target_stack().is_empty() {
+                    offered.push((Alternative::CloseStack, &self.stack_key));
+                }
+            }
+            _ => (),
+        }
+        // Alternatives whose key was disabled in configuration aren't offered
+        offered
+            .into_iter()
+            .filter_map(|(alternative, key)| key.as_ref().map(|key| (alternative, key)))
+            .collect()
+    }
 
-        // Confirmation text
-        let confirmation_text = self.action.confirmation_text().to_string();
-        let confirmation_y_location = (rows / 2) - 1;
-        let confirmation_x_location = cols.saturating_sub(confirmation_text.chars().count()) / 2;
-
-        print_text_with_coordinates(
-            Text::new(confirmation_text),
-            confirmation_x_location,
-            confirmation_y_location,
-            None,
-            None,
-        );
+    /// The keys confirming the current dialog, its action's own if it has any configured
+    fn dialog_confirm_keys(&self) -> &[KeyWithModifier] {
+        let action = self.dialog_action();
+        self.action_confirm_keys
+            .iter()
+            .find(|(configured, _)| *configured == action)
+            .map_or(&self.confirm_keys, |(_, keys)| keys)
+    }
 
-        // Show target info for pane/tab close
-        let target_info = match self.action {
-            Action::ClosePane => {
-                if let Some(pane_id) = &self.target_pane_id {
-                    match pane_id {
-                        PaneId::Terminal(id) => format!("Target: Terminal pane #{}", id),
-                        PaneId::Plugin(id) => format!("Target: Plugin pane #{}", id),
-                    }
-                } else {
-                    "Target: (detecting...)".to_string()
+    fn is_confirm(&self, key: &KeyWithModifier) -> bool {
+        contains_key(self.dialog_confirm_keys(), key)
+    }
+
+    /// Whether the key moves the selection to the other button, h and l too with `vim_keys`
+    fn is_button_switch(&self, key: &KeyWithModifier) -> bool {
+        match key.bare_key {
+            BareKey::Left | BareKey::Right | BareKey::Tab => true,
+            BareKey::Char('h') | BareKey::Char('l') => self.vim_keys && key.has_no_modifiers(),
+            _ => false,
+        }
+    }
+
+    fn is_cancel(&self, key: &KeyWithModifier) -> bool {
+        contains_key(&self.cancel_keys, key)
+    }
+
+    /// The confirm keys as shown in the help line, e.g. `ENTER/y`
+    fn confirm_key_label(&self) -> String {
+        key_label(self.dialog_confirm_keys())
+    }
+
+    /// The cancel keys as shown in the help line, e.g. `ESC/n`
+    fn cancel_key_label(&self) -> String {
+        key_label(&self.cancel_keys)
+    }
+
+    /// The armed alternative, if any, with its key as shown in the help line
+    fn armed_alternative_key(&self) -> Option<(Alternative, String)> {
+        let armed = self.armed_alternative?;
+        self.alternatives()
+            .into_iter()
+            .find(|(alternative, _)| *alternative == armed)
+            .map(|(alternative, key)| (alternative, key.to_string()))
+    }
+
+    fn alternative_for(&self, key: &KeyWithModifier) -> Option<Alternative> {
+        self.alternatives()
+            .into_iter()
+            .find(|(_, alternative_key)| *alternative_key == key)
+            .map(|(alternative, _)| alternative)
+    }
+
+    /// Whether an alternative takes a second press: closing the whole stack takes down more than
+    /// the pane, so it's gated like confirming, and interrupting an escalated target too
+    fn alternative_needs_second_press(&self, alternative: Alternative) -> bool {
+        match alternative {
+            Alternative::CloseStack => self.needs_second_press(),
+            Alternative::Interrupt => self.escalation_reason().is_some(),
+            _ => false,
+        }
+    }
+
+    /// Take an alternative by its key, arming it first if it takes a second press
+    fn pick_alternative(&mut self, alternative: Alternative) {
+        self.confirm_armed = false;
+        if self.alternative_needs_second_press(alternative)
+            && self.armed_alternative != Some(alternative)
+        {
+            self.armed_alternative = Some(alternative);
+            if self.double_confirm {
+                self.armed_deadline =
+                    Some(Instant::now() + Duration::from_secs(DOUBLE_CONFIRM_SECONDS));
+                self.schedule_countdown_tick();
+            }
+            return;
+        }
+        self.armed_alternative = None;
+        self.decide(alternative.description());
+        self.execute_alternative(alternative);
+    }
+
+    fn execute_alternative(&self, alternative: Alternative) {
+        match alternative {
+            Alternative::Detach => detach(),
+            Alternative::DisconnectOthers => {
+                hide_self();
+                disconnect_other_clients();
+            }
+            Alternative::Float => {
+                hide_self();
+                if let Some(pane_id) = self.target_pane_id {
+                    toggle_pane_embed_or_eject_for_pane_id(pane_id);
                 }
             }
-            Action::CloseTab => {
-                if let Some(tab_idx) = self.target_tab_index {
-                    format!("Target: Tab #{}", tab_idx + 1)
-                } else {
-                    "Target: (detecting...)".to_string()
+            Alternative::CloseStack => {
+                hide_self();
+                for pane_id in self.target_stack() {
+                    close_pane_with_id(pane_id);
                 }
             }
-            Action::QuitSession => String::new(),
+            Alternative::Interrupt => {
+                hide_self();
+                if let Some(pane_id) = self.target_pane_id {
+                    // ^C, same as pressing Ctrl+c in the pane
+                    write_to_pane_id(vec![0x03], pane_id);
+                }
+            }
+        }
+    }
+
+    /// Dismiss the dialog without doing anything
+    fn cancel(&mut self) {
+        self.decide("cancelled");
+        hide_self();
+    }
+
+    /// Start, or restart, the `timeout_seconds` countdown after which the dialog answers itself
+    fn start_countdown(&mut self) {
+        if let Some(timeout) = self.timeout {
+            self.countdown_deadline = Some(Instant::now() + timeout);
+            self.schedule_countdown_tick();
+        }
+    }
+
+    /// Show `KEY_HINT` for a moment
+    fn flash_key_hint(&mut self) {
+        self.key_hint_deadline = Some(Instant::now() + Duration::from_secs(KEY_HINT_SECONDS));
+        self.schedule_countdown_tick();
+    }
+
+    fn schedule_countdown_tick(&mut self) {
+        if !self.countdown_tick_pending {
+            self.countdown_tick_pending = true;
+            set_timeout(1.0);
+        }
+    }
+
+    /// Whole seconds left on the countdown, rounded up, if one is running
+    fn countdown_remaining(&self) -> Option<u64> {
+        let remaining = self
+            .countdown_deadline?
+            .saturating_duration_since(Instant::now());
+        Some(remaining.as_millis().div_ceil(1000) as u64)
+    }
+
+    /// Answer the dialog with the `timeout_action` once its countdown ran out, else wait for the
+    /// next second
+    fn tick_countdown(&mut self) {
+        self.countdown_tick_pending = false;
+        if let Some(skip_check_deadline) = self.skip_check_deadline {
+            if Instant::now() >= skip_check_deadline {
+                self.skip_check_deadline = None;
+                self.skip_check_pending = false;
+            } else {
+                self.schedule_countdown_tick();
+            }
+        }
+        if let Some(key_hint_deadline) = self.key_hint_deadline {
+            if Instant::now() >= key_hint_deadline {
+                self.key_hint_deadline = None;
+            } else {
+                self.schedule_countdown_tick();
+            }
+        }
+        if let Some(armed_deadline) = self.armed_deadline {
+            if Instant::now() >= armed_deadline {
+                // Too slow for a double press, the next one counts as the first again
+                self.armed_deadline = None;
+                self.confirm_armed = false;
+                self.armed_alternative = None;
+            } else {
+                self.schedule_countdown_tick();
+            }
+        }
+        if let Some(result_deadline) = self.result_deadline {
+            if Instant::now() >= result_deadline {
+                self.dismiss_result();
+            } else {
+                self.schedule_countdown_tick();
+            }
+            return;
+        }
+        if let Some(quit_deadline) = self.quit_deadline {
+            if Instant::now() >= quit_deadline {
+                self.quit_deadline = None;
+                self.run_confirmed_action();
+            } else {
+                self.schedule_countdown_tick();
+            }
+            return;
+        }
+        let Some(deadline) = self.countdown_deadline else {
+            return;
         };
+        if self.error.is_some() || self.is_busy() {
+            // The dialog was answered in the meantime
+            self.countdown_deadline = None;
+        } else if Instant::now() >= deadline {
+            self.countdown_deadline = None;
+            if self.countdown_confirms() {
+                self.confirm_armed = false;
+                self.armed_alternative = None;
+                self.execute_action();
+            } else {
+                self.cancel();
+            }
+        } else {
+            self.schedule_countdown_tick();
+        }
+    }
 
-        if !target_info.is_empty() {
-            let info_y = (rows / 2) + 1;
-            let info_x = cols.saturating_sub(target_info.chars().count()) / 2;
-            print_text_with_coordinates(
-                Text::new(target_info).color_range(1, 0..7), // Color "Target:" prefix
-                info_x,
-                info_y,
-                None,
-                None,
-            );
+    /// Let the requester and, with `broadcast_message`, every other plugin know what was decided
+    fn decide(&mut self, decision: &str) {
+        self.answered = true;
+        self.report_decision(decision);
+        if let Some(name) = &self.broadcast_message {
+            let payload = self
+                .broadcast_payload
+                .replace("{decision}", decision)
+                .replace("{action}", &self.dialog_action().title(&self.strings));
+            // Without a destination the message goes to every running plugin
+            pipe_message_to_plugin(MessageToPlugin::new(name).with_payload(payload));
         }
+    }
 
-        // Help text at bottom - [y/N] convention
-        let help_text = "[y/N]";
-        let help_text_y_location = rows - 1;
-        let help_text_x_location = cols.saturating_sub(help_text.chars().count()) / 2;
+    /// Tell whoever opened the dialog through a pipe what was decided
+    fn report_decision(&mut self, decision: &str) {
+        match self.requester.take() {
+            Some(Requester::CliPipe(pipe_id)) => {
+                cli_pipe_output(&pipe_id, &format!("{}\n", decision));
+                unblock_cli_pipe_input(&pipe_id);
+            }
+            Some(Requester::Plugin {
+                plugin_id,
+                request_id,
+            }) => {
+                let mut args = BTreeMap::new();
+                if let Some(request_id) = request_id {
+                    args.insert("request_id".to_string(), request_id);
+                }
+                pipe_message_to_plugin(
+                    MessageToPlugin::new("confirm_result")
+                        .with_destination_plugin_id(plugin_id)
+                        .with_payload(decision)
+                        .with_args(args),
+                );
+            }
+            None => (),
+        }
+    }
 
-        print_text_with_coordinates(
-            Text::new(help_text)
-                .color_range(3, 1..2) // 'y' in color
-                .color_range(0, 3..4), // 'N' in different color
-            help_text_x_location,
-            help_text_y_location,
-            None,
-            None,
-        );
+    /// Run the confirmed action, step by step
+    fn execute_action(&mut self) {
+        if let Some(abort_window) = self.abort_window {
+            if self.dialog_action().steps().contains(&Action::QuitSession) {
+                // One last chance to take back a reflexive confirmation
+                self.countdown_deadline = None;
+                self.quit_deadline = Some(Instant::now() + abort_window);
+                self.schedule_countdown_tick();
+                return;
+            }
+        }
+        self.run_confirmed_action();
     }
-}
 
-impl State {
-    fn execute_action(&self) {
-        match self.action {
+    /// Run the confirmed action's steps
+    fn run_confirmed_action(&mut self) {
+        // Reply first, quitting the session would take the plugin down with it
+        self.decide("confirmed");
+        if self.permissions_denied {
+            self.fail(self.strings.get("error.permissions_denied").to_string());
+            return;
+        }
+        if self.result_duration.is_some() {
+            // Counted before the steps run, while the targets still exist
+            let results: Vec<String> = self
+                .dialog_action()
+                .steps()
+                .iter()
+                .filter_map(|step| self.step_result(step))
+                .collect();
+            if !results.is_empty() {
+                self.pending_result = Some(results.join(", "));
+            }
+        }
+        self.pending_steps = self.dialog_action().steps().iter().cloned().collect();
+        self.run_pending_steps();
+    }
+
+    /// Run queued steps until they're done, one fails, or one has to wait for zellij
+    fn run_pending_steps(&mut self) {
+        while let Some(step) = self.pending_steps.pop_front() {
+            if let Err(error) = self.execute_step(&step) {
+                self.fail(error);
+                return;
+            }
+            if self.is_busy() {
+                return;
+            }
+        }
+        if let (Some(result), Some(result_duration)) =
+            (self.pending_result.take(), self.result_duration)
+        {
+            self.result = Some(result);
+            self.result_deadline = Some(Instant::now() + result_duration);
+            self.schedule_countdown_tick();
+            show_self(true);
+        }
+    }
+
+    /// What closing a pane or tab did, for the result screen, e.g. `Closed tab 'build' and its
+    /// 4 panes`
+    fn step_result(&self, step: &Action) -> Option<String> {
+        let closed_panes =
+            |count: usize| self.strings.format_count("result.closed_panes", count, &[]);
+        let closed_tabs =
+            |count: usize| self.strings.format_count("result.closed_tabs", count, &[]);
+        match step {
+            Action::ClosePane => {
+                let pane_id = self.target_pane_id?;
+                let id = pane_id_number(&pane_id).to_string();
+                Some(self.strings.format("result.closed_pane", &[("id", &id)]))
+            }
+            Action::CloseOtherPanes => Some(closed_panes(self.other_pane_ids().len())),
+            Action::CloseFloatingPanes => Some(closed_panes(self.floating_pane_ids().len())),
+            Action::CloseExitedPanes => Some(closed_panes(self.exited_pane_ids().len())),
+            Action::CloseIdlePanes => Some(closed_panes(self.selected_idle_pane_ids().len())),
+            Action::CloseTab => {
+                let tab = self.target_tab()?;
+                let count = self.tab_pane_count(tab.position);
+                Some(
+                    self.strings
+                        .format_count("result.closed_tab", count, &[("name", &tab.name)]),
+                )
+            }
+            Action::CloseOtherTabs => Some(closed_tabs(self.other_tab_positions().len())),
+            Action::CloseTabsToRight => Some(closed_tabs(self.tabs_to_the_right().len())),
+            _ => None,
+        }
+    }
+
+    /// Leave the result screen
+    fn dismiss_result(&mut self) {
+        self.result = None;
+        self.result_deadline = None;
+        hide_self();
+    }
+
+    /// Abort the remaining steps and show the error screen
+    fn fail(&mut self, error: String) {
+        self.pending_steps.clear();
+        self.pending_result = None;
+        self.awaiting_layout = false;
+        self.interrupt_queue.clear();
+        self.error = Some(error);
+        show_self(true);
+    }
+
+    /// Whether the dialog itself is up, rather than one of the screens shown after confirming
+    fn shows_dialog(&self) -> bool {
+        self.config_problems.is_empty()
+            && self.error.is_none()
+            && self.result.is_none()
+            && self.quit_deadline.is_none()
+            && !self.is_busy()
+    }
+
+    /// Whether a step is still in progress, so the remaining steps have to wait
+    fn is_busy(&self) -> bool {
+        self.awaiting_layout || !self.interrupt_queue.is_empty()
+    }
+
+    /// Interrupt the next queued pane, then continue with the remaining steps once all are done
+    fn interrupt_next_pane(&mut self) {
+        if let Some(pane_id) = self.interrupt_queue.pop_front() {
+            write_to_pane_id(vec![0x03], pane_id);
+        }
+        if self.interrupt_queue.is_empty() {
+            self.interrupt_due = None;
+            hide_self();
+            self.run_pending_steps();
+        } else {
+            self.interrupt_due =
+                Some(Instant::now() + Duration::from_secs_f64(INTERRUPT_INTERVAL_SECS));
+            set_timeout(INTERRUPT_INTERVAL_SECS);
+        }
+    }
+
+    /// Write the dumped session layout to the configured file in the host folder
+    fn save_layout(&self, layout: &str) -> Result<(), String> {
+        let path = std::path::Path::new("/host").join(&self.layout_file);
+        std::fs::write(&path, layout).map_err(|error| {
+            self.strings.format(
+                "error.write_layout",
+                &[("file", &self.layout_file), ("error", &error.to_string())],
+            )
+        })?;
+        hide_self();
+        Ok(())
+    }
+
+    /// Execute a single action, reporting why it couldn't be issued
+    fn execute_step(&mut self, step: &Action) -> Result<(), String> {
+        match step {
             Action::QuitSession => {
                 quit_zellij();
             }
+            Action::DetachSession => {
+                detach();
+            }
+            Action::KillAllSessions => {
+                let other_sessions: Vec<&str> = self
+                    .sessions
+                    .iter()
+                    .filter(|session| !session.is_current_session)
+                    .map(|session| session.name.as_str())
+                    .collect();
+                if !other_sessions.is_empty() {
+                    kill_sessions(&other_sessions);
+                }
+                // The current session goes last, since quitting it tears down this plugin
+                quit_zellij();
+            }
+            Action::KillAndDeleteSession => {
+                match self.current_session() {
+                    Some(session) => {
+                        // `delete-session --force` kills the session and removes its
+                        // resurrection data in one go
+                        let context = BTreeMap::from([(
+                            DELETE_SESSION_CONTEXT.to_string(),
+                            session.name.clone(),
+                        )]);
+                        run_command(
+                            &["zellij", "delete-session", "--force", &session.name],
+                            context,
+                        );
+                    }
+                    // Without a session name we can only quit, leaving it resurrectable
+                    None => quit_zellij(),
+                }
+            }
+            Action::KillSession => {
+                let Some(name) = &self.session_name else {
+                    return Err(self.strings.get("error.no_session_name").to_string());
+                };
+                let Some(session) = self.target_session() else {
+                    return Err(self
+                        .strings
+                        .format("error.session_not_killable", &[("name", name)]));
+                };
+                hide_self();
+                kill_sessions(&[session.name.as_str()]);
+            }
             Action::ClosePane => {
+                // Closing whatever has focus instead could close this plugin or the wrong pane
+                let Some(pane_id) = self.target_pane_id else {
+                    return Err(self.strings.get("error.no_target_pane").to_string());
+                };
+                if !self.panes.panes.is_empty() && self.pane(&pane_id).is_none() {
+                    let id = pane_id_number(&pane_id).to_string();
+                    return Err(self.strings.format("error.pane_gone", &[("id", &id)]));
+                }
                 // First hide ourselves, then close the target pane
                 hide_self();
-                if let Some(pane_id) = &self.target_pane_id {
-                    close_pane_with_id(pane_id.clone());
+                close_pane_with_id(pane_id);
+                if let Some(focus_after_close) = self.focus_after_close {
+                    focus_after_close.apply();
+                }
+            }
+            Action::CloseOtherPanes => {
+                hide_self();
+                for pane_id in self.other_pane_ids() {
+                    close_pane_with_id(pane_id);
+                }
+            }
+            Action::RestartPane => {
+                // Only command panes have a command to re-run
+                let Some(pane) = self
+                    .target_pane()
+                    .filter(|pane| !pane.is_plugin && pane.terminal_command.is_some())
+                else {
+                    return Err(self.strings.get("error.not_command_pane").to_string());
+                };
+                hide_self();
+                rerun_command_pane(pane.id);
+            }
+            Action::CloseFloatingPanes => {
+                hide_self();
+                for pane_id in self.floating_pane_ids() {
+                    close_pane_with_id(pane_id);
+                }
+            }
+            Action::CloseExitedPanes => {
+                hide_self();
+                for pane_id in self.exited_pane_ids() {
+                    close_pane_with_id(pane_id);
+                }
+            }
+            Action::InterruptAll => {
+                self.interrupt_queue = self.interruptible_pane_ids().into();
+                self.interrupt_total = self.interrupt_queue.len();
+                if self.interrupt_queue.is_empty() {
+                    hide_self();
                 } else {
-                    // Fallback: if we couldn't identify the pane, use close_focus
-                    // This might close the plugin itself, but it's better than nothing
-                    close_focus();
+                    // The dialog is answered, whatever its timers were counting down to is moot
+                    self.countdown_deadline = None;
+                    self.key_hint_deadline = None;
+                    self.armed_deadline = None;
+                    // The first pane is interrupted on the first tick, so progress shows right away
+                    self.interrupt_due = Some(Instant::now());
+                    set_timeout(0.0);
                 }
             }
             Action::CloseTab => {
-                // First hide ourselves, then close the tab
+                self.resolve_target_tab();
+                if !self.tabs.is_empty() && self.target_tab().is_none() {
+                    return Err(self.strings.get("error.tab_gone").to_string());
+                }
+                // First hide ourselves, then close the tab the dialog was about, which needn't
+                // be the focused one by now
+                hide_self();
+                match self.target_tab_index {
+                    Some(position) => close_tab_with_index(position),
+                    None => close_focused_tab(),
+                }
+                let position_after_close = self
+                    .focus_tab_after_close
+                    .zip(self.target_tab_index)
+                    .and_then(|(focus_tab_after_close, tab_index)| {
+                        focus_tab_after_close.position_after_close(tab_index, self.tabs.len())
+                    });
+                if let Some(position) = position_after_close {
+                    // `switch_tab_to` counts tabs from 1
+                    switch_tab_to(position as u32 + 1);
+                }
+            }
+            Action::CloseOtherTabs => {
+                hide_self();
+                // Close from the rightmost tab so the remaining positions stay valid
+                for position in self.other_tab_positions().into_iter().rev() {
+                    close_tab_with_index(position);
+                }
+            }
+            Action::CloseTabsToRight => {
+                hide_self();
+                // Close from the rightmost tab so the remaining positions stay valid
+                for tab in self.tabs_to_the_right().into_iter().rev() {
+                    close_tab_with_index(tab.position);
+                }
+            }
+            Action::CloseIdlePanes => {
+                let pane_ids = self.selected_idle_pane_ids();
+                if pane_ids.is_empty() {
+                    return Err(self.strings.get("error.no_idle_panes").to_string());
+                }
                 hide_self();
-                close_focused_tab();
+                for pane_id in pane_ids {
+                    close_pane_with_id(pane_id);
+                }
+            }
+            Action::DumpLayout => {
+                // The layout arrives as a CustomMessage, the remaining steps resume from there
+                self.awaiting_layout = true;
+                dump_session_layout();
+            }
+            Action::Confirm => {
+                // The decision was all that was asked for
+                hide_self();
+            }
+            Action::Chain(steps) => {
+                for step in steps {
+                    self.execute_step(step)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Where a pane is, from the session down, e.g.
+    /// `work ▸ Tab 3 "build" ▸ pane #12 (cargo watch)`
+    fn pane_breadcrumb(&self, pane_id: &PaneId) -> String {
+        let mut crumbs = Vec::new();
+        if let Some(session_name) = &self.current_session_name {
+            crumbs.push(session_name.clone());
+        }
+        if let Some(tab_position) = self.pane_tab_position(pane_id) {
+            let position = (tab_position + 1).to_string();
+            crumbs.push(match self.pane_tab_name(pane_id) {
+                Some(name) => self.strings.format(
+                    "target.tab_crumb",
+                    &[("position", &position), ("name", name)],
+                ),
+                None => self
+                    .strings
+                    .format("target.unnamed_tab_crumb", &[("position", &position)]),
+            });
+        }
+        let id = pane_id_number(pane_id).to_string();
+        crumbs.push(match (pane_id, self.pane_command(pane_id)) {
+            (PaneId::Terminal(_), Some(command)) => self
+                .strings
+                .format("target.pane_crumb", &[("id", &id), ("command", command)]),
+            (PaneId::Terminal(_), None) => self
+                .strings
+                .format("target.bare_pane_crumb", &[("id", &id)]),
+            (PaneId::Plugin(_), _) => self.strings.format("target.plugin_crumb", &[("id", &id)]),
+        });
+        let separator = if self.ascii_only { " > " } else { " ▸ " };
+        crumbs.join(separator)
+    }
+
+    /// How a command pane finished, if it did, e.g.
+    /// `Command pane #3 (cargo test) exited with code 1 after 2m 3s, held`
+    fn finished_pane_info(&self, pane: &PaneInfo) -> Option<String> {
+        let command = pane.terminal_command.as_ref()?;
+        let mut info = self.command_pane_info(pane.id, command);
+        if pane.exited {
+            info.push_str(self.strings.get("target.exited"));
+            if let Some(exit_status) = pane.exit_status {
+                let code = exit_status.to_string();
+                info.push_str(&self.strings.format("target.exit_code", &[("code", &code)]));
+            }
+            if let Some(runtime) = self.command_runtimes.get(&pane.id) {
+                let duration = format_duration(runtime.as_secs());
+                info.push_str(
+                    &self
+                        .strings
+                        .format("target.runtime", &[("duration", &duration)]),
+                );
+            }
+        }
+        if pane.is_held {
+            // Held panes wait for Enter to re-run or Ctrl-c to close
+            info.push_str(self.strings.get("target.held"));
+        }
+        Some(info)
+    }
+
+    /// A command pane with its command, e.g. `Command pane #3 (cargo test)`
+    fn command_pane_info(&self, id: u32, command: &str) -> String {
+        let id = id.to_string();
+        self.strings
+            .format("target.command_pane", &[("id", &id), ("command", command)])
+    }
+
+    /// The dialog's confirmation text: a pipe's custom message, else the configured
+    /// `<action>_text`, else the action's own
+    ///
+    /// In custom texts a literal `\n`, as left by `--args` or raw KDL strings, breaks the line
+    /// like a newline.
+    fn confirmation_text(&self, action: &Action) -> String {
+        let custom_text = self.custom_message.as_ref().or_else(|| {
+            self.confirmation_texts
+                .iter()
+                .find(|(configured, _)| configured == action)
+                .map(|(_, text)| text)
+        });
+        match custom_text {
+            Some(text) => self.fill_placeholders(&text.replace("\\n", "\n")),
+            None => action.confirmation_text(&self.strings),
+        }
+    }
+
+    /// Fill in the `{session_name}`, `{tab_name}`, `{pane_title}`, `{pane_count}` and
+    /// `{tab_count}` placeholders of a custom text from the latest session, tab and pane info
+    fn fill_placeholders(&self, text: &str) -> String {
+        let pane_title = self
+            .target_pane_id
+            .as_ref()
+            .and_then(|pane_id| self.pane(pane_id))
+            .map(|pane| pane.title.as_str());
+        text.replace(
+            "{session_name}",
+            self.current_session_name.as_deref().unwrap_or(""),
+        )
+        .replace(
+            "{tab_name}",
+            self.target_tab().map(|tab| tab.name.as_str()).unwrap_or(""),
+        )
+        .replace("{pane_title}", pane_title.unwrap_or(""))
+        .replace("{pane_count}", &self.session_pane_count().to_string())
+        .replace("{tab_count}", &self.tabs.len().to_string())
+    }
+
+    /// Describe what the given action will act on, or an empty string if there's nothing to show
+    fn target_info(&self, action: &Action) -> String {
+        match action {
+            Action::ClosePane => {
+                if let Some(pane_id) = &self.target_pane_id {
+                    let pane = match (pane_id, self.target_pane()) {
+                        (PaneId::Terminal(_), Some(pane)) if pane.exited || pane.is_held => self
+                            .finished_pane_info(pane)
+                            .unwrap_or_else(|| self.pane_breadcrumb(pane_id)),
+                        _ => self.pane_breadcrumb(pane_id),
+                    };
+                    match self.target_stack().len() {
+                        0 => pane,
+                        stack_size => self.strings.format(
+                            "target.in_stack",
+                            &[("pane", &pane), ("count", &stack_size.to_string())],
+                        ),
+                    }
+                } else {
+                    self.strings.get("target.detecting").to_string()
+                }
             }
+            Action::CloseOtherPanes => {
+                if self.target_pane_id.is_some() {
+                    let count = self.other_pane_ids().len();
+                    self.strings.format_count("target.other_panes", count, &[])
+                } else {
+                    self.strings.get("target.detecting").to_string()
+                }
+            }
+            Action::RestartPane => match self.target_pane() {
+                Some(pane) => match &pane.terminal_command {
+                    Some(command) => self.command_pane_info(pane.id, command),
+                    None => self
+                        .strings
+                        .format("target.terminal_pane", &[("id", &pane.id.to_string())]),
+                },
+                None => self.strings.get("target.detecting").to_string(),
+            },
+            Action::CloseFloatingPanes => {
+                if self.target_tab_index.is_some() {
+                    let count = self.floating_pane_ids().len();
+                    self.strings
+                        .format_count("target.floating_panes", count, &[])
+                } else {
+                    self.strings.get("target.detecting").to_string()
+                }
+            }
+            Action::CloseExitedPanes => {
+                let count = self.exited_pane_ids().len();
+                self.strings.format_count("target.exited_panes", count, &[])
+            }
+            Action::InterruptAll => {
+                if self.interrupt_tab_only && self.target_tab_index.is_none() {
+                    self.strings.get("target.detecting").to_string()
+                } else {
+                    let count = self.interruptible_pane_ids().len();
+                    let id = if self.interrupt_tab_only {
+                        "target.tab_panes"
+                    } else {
+                        "target.session_panes"
+                    };
+                    self.strings.format_count(id, count, &[])
+                }
+            }
+            // Looked up on every render, so a rename while the dialog is open shows up
+            Action::CloseTab => match (self.target_tab(), self.target_tab_index) {
+                (Some(tab), _) => self.strings.format("target.tab", &[("name", &tab.name)]),
+                (None, Some(tab_idx)) => self.strings.format(
+                    "target.tab_number",
+                    &[("position", &(tab_idx + 1).to_string())],
+                ),
+                (None, None) => self.strings.get("target.detecting").to_string(),
+            },
+            Action::CloseOtherTabs => {
+                if self.target_tab_index.is_some() {
+                    let count = self.other_tab_positions().len();
+                    self.strings.format_count("target.other_tabs", count, &[])
+                } else {
+                    self.strings.get("target.detecting").to_string()
+                }
+            }
+            Action::CloseTabsToRight => {
+                if self.target_tab_index.is_some() {
+                    let tabs = self.tabs_to_the_right();
+                    let names: Vec<&str> = tabs.iter().map(|tab| tab.name.as_str()).collect();
+                    if names.is_empty() {
+                        self.strings.get("target.no_tabs_to_right").to_string()
+                    } else {
+                        self.strings.format_count(
+                            "target.tabs_to_right",
+                            names.len(),
+                            &[("names", &names.join(", "))],
+                        )
+                    }
+                } else {
+                    self.strings.get("target.detecting").to_string()
+                }
+            }
+            Action::KillAllSessions => {
+                if !self.sessions.is_empty() {
+                    let count = self.sessions.len();
+                    self.strings.format_count("target.sessions", count, &[])
+                } else {
+                    self.strings.get("target.detecting").to_string()
+                }
+            }
+            Action::KillSession => match &self.session_name {
+                Some(name) => match self.sessions.iter().find(|session| &session.name == name) {
+                    Some(session) if session.is_current_session => self
+                        .strings
+                        .format("target.current_session", &[("name", name)]),
+                    Some(_) => self.strings.format("target.session", &[("name", name)]),
+                    None => self
+                        .strings
+                        .format("target.session_not_running", &[("name", name)]),
+                },
+                None => self.strings.get("target.no_session_name").to_string(),
+            },
+            Action::KillAndDeleteSession => match self.current_session() {
+                Some(session) => self
+                    .strings
+                    .format("target.session", &[("name", &session.name)]),
+                None => self.strings.get("target.detecting").to_string(),
+            },
+            Action::DumpLayout => self.layout_file.clone(),
+            Action::Confirm => String::new(),
+            Action::CloseIdlePanes => {
+                if self.idle_scan_pending {
+                    self.strings.get("target.scanning").to_string()
+                } else {
+                    let count = self.idle_panes.len();
+                    let selected = self.selected_idle_pane_ids().len().to_string();
+                    self.strings.format_count(
+                        "target.idle_panes",
+                        count,
+                        &[("selected", &selected)],
+                    )
+                }
+            }
+            Action::Chain(steps) => steps
+                .iter()
+                .map(|step| self.target_info(step))
+                .filter(|info| !info.is_empty())
+                .collect::<Vec<_>>()
+                // Every step's target gets its own prefix, the first one's is added by `render`
+                .join(&format!(" | {} ", self.strings.get("target"))),
+            Action::QuitSession | Action::DetachSession => String::new(),
+        }
+    }
+
+    /// The latest info about the target pane, if it is still in the manifest
+    fn target_pane(&self) -> Option<&PaneInfo> {
+        self.pane(&self.target_pane_id?)
+    }
+
+    /// The latest info about a pane anywhere in the session
+    fn pane(&self, pane_id: &PaneId) -> Option<&PaneInfo> {
+        self.panes
+            .panes
+            .values()
+            .flatten()
+            .find(|pane| pane_id_of(pane) == *pane_id)
+    }
+
+    /// Time command pane runs, from the first update seeing them running to the first seeing
+    /// them exited
+    fn track_command_panes(&mut self) {
+        let now = Instant::now();
+        let command_panes = self
+            .panes
+            .panes
+            .values()
+            .flatten()
+            .filter(|pane| !pane.is_plugin && pane.terminal_command.is_some());
+        for pane in command_panes {
+            if !pane.exited {
+                self.command_started.entry(pane.id).or_insert(now);
+            } else if let Some(started) = self.command_started.remove(&pane.id) {
+                self.command_runtimes.insert(pane.id, now - started);
+            }
+        }
+    }
+
+    /// Ids of the terminal panes closing the target pane, tab or session would take down
+    fn affected_terminal_pane_ids(&self) -> Vec<PaneId> {
+        let mut pane_ids = Vec::new();
+        for step in self.dialog_action().steps() {
+            let panes: Vec<&PaneInfo> = match step {
+                Action::ClosePane => self.target_pane().into_iter().collect(),
+                Action::CloseTab => self
+                    .target_tab_index
+                    .and_then(|tab_index| self.panes.panes.get(&tab_index))
+                    .map(|panes| panes.iter().collect())
+                    .unwrap_or_default(),
+                Action::QuitSession => self.panes.panes.values().flatten().collect(),
+                _ => Vec::new(),
+            };
+            for pane in panes.into_iter().filter(|pane| !pane.is_plugin) {
+                let pane_id = pane_id_of(pane);
+                if !pane_ids.contains(&pane_id) {
+                    pane_ids.push(pane_id);
+                }
+            }
+        }
+        pane_ids
+    }
+
+    /// Ids of every pane stacked with the target pane (including it), or empty if it isn't
+    /// in a stack
+    ///
+    /// The manifest doesn't expose stacks, so they are inferred from geometry: a stack is a
+    /// column of tiled panes with the same x/width where every pane but the expanded one is
+    /// collapsed to its single title row.
+    fn target_stack(&self) -> Vec<PaneId> {
+        let (Some(tab_index), Some(target)) = (self.target_tab_index, self.target_pane()) else {
+            return Vec::new();
+        };
+        if target.is_floating {
+            return Vec::new();
         }
+        let mut column: Vec<&PaneInfo> = self
+            .panes
+            .panes
+            .get(&tab_index)
+            .map(|panes| {
+                panes
+                    .iter()
+                    .filter(|pane| !pane.is_floating && !pane.is_suppressed && pane.is_selectable)
+                    .filter(|pane| {
+                        pane.pane_x == target.pane_x && pane.pane_columns == target.pane_columns
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        column.sort_by_key(|pane| pane.pane_y);
+        let Some(target_index) = column.iter().position(|pane| pane.id == target.id) else {
+            return Vec::new();
+        };
+
+        // Walk away from the target over adjacent collapsed panes, in both directions
+        let mut first = target_index;
+        while first > 0
+            && column[first - 1].pane_rows == 1
+            && column[first - 1].pane_y + 1 == column[first].pane_y
+        {
+            first -= 1;
+        }
+        let mut last = target_index;
+        while last + 1 < column.len()
+            && column[last + 1].pane_rows == 1
+            && column[last].pane_y + column[last].pane_rows == column[last + 1].pane_y
+        {
+            last += 1;
+        }
+        if first == last {
+            return Vec::new();
+        }
+        column[first..=last]
+            .iter()
+            .map(|pane| pane_id_of(pane))
+            .collect()
+    }
+
+    /// The session this plugin is running in
+    fn current_session(&self) -> Option<&SessionInfo> {
+        self.sessions
+            .iter()
+            .find(|session| session.is_current_session)
+    }
+
+    /// The running session named by `session_name`, unless it is the current one
+    fn target_session(&self) -> Option<&SessionInfo> {
+        let name = self.session_name.as_ref()?;
+        self.sessions
+            .iter()
+            .find(|session| &session.name == name && !session.is_current_session)
+    }
+
+    /// Ids of every pane in the target tab except the target pane, skipping UI bars
+    /// and (unless `close_plugin_panes` is set) plugin panes
+    fn other_pane_ids(&self) -> Vec<PaneId> {
+        let Some(tab_index) = self.target_tab_index else {
+            return Vec::new();
+        };
+        self.panes
+            .panes
+            .get(&tab_index)
+            .map(|panes| {
+                panes
+                    .iter()
+                    .filter(|pane| pane.is_selectable)
+                    .filter(|pane| {
+                        !pane.is_plugin || (self.close_plugin_panes && pane.id != self.plugin_id)
+                    })
+                    .map(pane_id_of)
+                    .filter(|pane_id| Some(*pane_id) != self.target_pane_id)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Ids of every floating pane in the target tab, except this plugin's own pane
+    fn floating_pane_ids(&self) -> Vec<PaneId> {
+        let Some(tab_index) = self.target_tab_index else {
+            return Vec::new();
+        };
+        let own_pane_id = PaneId::Plugin(self.plugin_id);
+        self.panes
+            .panes
+            .get(&tab_index)
+            .map(|panes| {
+                panes
+                    .iter()
+                    .filter(|pane| pane.is_floating && pane.is_selectable)
+                    .map(pane_id_of)
+                    .filter(|pane_id| *pane_id != own_pane_id)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Ids of every command pane in the session whose command exited
+    fn exited_pane_ids(&self) -> Vec<PaneId> {
+        self.panes
+            .panes
+            .values()
+            .flatten()
+            .filter(|pane| !pane.is_plugin && pane.terminal_command.is_some() && pane.exited)
+            .map(pane_id_of)
+            .collect()
+    }
+
+    /// Ids of the terminal panes InterruptAll sends ^C to, skipping exited command panes
+    fn interruptible_pane_ids(&self) -> Vec<PaneId> {
+        self.panes
+            .panes
+            .iter()
+            .filter(|(tab_index, _)| {
+                !self.interrupt_tab_only || Some(**tab_index) == self.target_tab_index
+            })
+            .flat_map(|(_, panes)| panes)
+            .filter(|pane| !pane.is_plugin && !pane.exited && pane.is_selectable)
+            .map(pane_id_of)
+            .collect()
+    }
+
+    /// Positions of every tab except the target one, in ascending order
+    fn other_tab_positions(&self) -> Vec<usize> {
+        let mut positions: Vec<usize> = self
+            .tabs
+            .iter()
+            .map(|tab| tab.position)
+            .filter(|position| Some(*position) != self.target_tab_index)
+            .collect();
+        positions.sort_unstable();
+        positions
+    }
+
+    /// Tabs positioned after the target tab, in ascending order of position
+    fn tabs_to_the_right(&self) -> Vec<&TabInfo> {
+        let Some(tab_index) = self.target_tab_index else {
+            return Vec::new();
+        };
+        let mut tabs: Vec<&TabInfo> = self
+            .tabs
+            .iter()
+            .filter(|tab| tab.position > tab_index)
+            .collect();
+        tabs.sort_unstable_by_key(|tab| tab.position);
+        tabs
     }
 }
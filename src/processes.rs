@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use zellij_tile::prelude::*;
+
+/// The namespace the worker is registered under
+pub const WORKER_NAME: &str = "processes";
+/// Sent to the worker with the output of `LIST_SCRIPT`
+pub const PARSE_MESSAGE: &str = "parse_processes";
+/// Sent back to the plugin with one indented process per line
+pub const RESULT_MESSAGE: &str = "pane_processes";
+/// Sent back to the plugin as `<elapsed seconds> <args>` for the pane's foreground command
+pub const FOREGROUND_MESSAGE: &str = "pane_foreground";
+/// Sent to the worker with the output of `PANES_SCRIPT`, for the idle panes
+pub const PARSE_IDLE_MESSAGE: &str = "parse_idle_panes";
+/// Sent back to the plugin with the space-separated ids of the panes with nothing running
+pub const IDLE_MESSAGE: &str = "idle_panes";
+/// Sent to the worker with the output of `PANES_SCRIPT`, for the commands the panes run
+pub const PARSE_COMMANDS_MESSAGE: &str = "parse_pane_commands";
+/// Sent back to the plugin as one `<pane id> <children> <args>` line per pane, for how many
+/// processes run below its shell and its foreground command
+pub const COMMANDS_MESSAGE: &str = "pane_commands";
+
+/// Prints `root <pid>` for the shell of the pane `$2` among the children of the zellij server
+/// `$1`, followed by `ps` output for every process so the worker can walk the tree below it.
+/// Panes are told apart by the `ZELLIJ_PANE_ID` zellij sets in their environment.
+pub const LIST_SCRIPT: &str = r#"
+for pid in $(pgrep -P "$1"); do
+    tr '\0' '\n' < "/proc/$pid/environ" 2>/dev/null | grep -qx "ZELLIJ_PANE_ID=$2" && echo "root $pid"
+done
+ps -eo pid=,ppid=,pgid=,tpgid=,etimes=,args=
+"#;
+
+/// The `run_command` context keys of `PANES_SCRIPT`, for the idle panes and the commands the
+/// panes run
+pub const IDLE_CONTEXT: &str = "idle_panes";
+pub const COMMANDS_CONTEXT: &str = "pane_commands";
+
+/// Prints `pane <pane id> <pid>` for the first process of every pane of the zellij server `$1`,
+/// followed by the same `ps` output as `LIST_SCRIPT`
+pub const PANES_SCRIPT: &str = r#"
+for pid in $(pgrep -P "$1"); do
+    pane=$(tr '\0' '\n' < "/proc/$pid/environ" 2>/dev/null | sed -n 's/^ZELLIJ_PANE_ID=//p')
+    [ -n "$pane" ] && echo "pane $pane $pid"
+done
+ps -eo pid=,ppid=,pgid=,tpgid=,etimes=,args=
+"#;
+
+/// The `run_command` context key of `GIT_STATUS_SCRIPT`
+pub const GIT_STATUS_CONTEXT: &str = "git_status";
+
+/// Prints the top level of every git worktree with uncommitted changes that the shell of one of
+/// the panes `$2...` of the zellij server `$1` is in
+pub const GIT_STATUS_SCRIPT: &str = r#"
+server=$1
+shift
+for pid in $(pgrep -P "$server"); do
+    pane=$(tr '\0' '\n' < "/proc/$pid/environ" 2>/dev/null | sed -n 's/^ZELLIJ_PANE_ID=//p')
+    case " $* " in *" $pane "*) ;; *) continue ;; esac
+    cwd=$(readlink "/proc/$pid/cwd") || continue
+    if [ -n "$(git -C "$cwd" status --porcelain 2>/dev/null | head -n 1)" ]; then
+        git -C "$cwd" rev-parse --show-toplevel
+    fi
+done | sort -u
+"#;
+
+/// Builds the process tree of the target pane off the plugin's render loop
+#[derive(Default, Serialize, Deserialize)]
+pub struct ProcessWorker {}
+
+impl ZellijWorker<'_> for ProcessWorker {
+    fn on_message(&mut self, message: String, payload: String) {
+        if message == PARSE_MESSAGE {
+            let tree = process_tree(&payload).join("\n");
+            post_message_to_plugin(PluginMessage::new_to_plugin(RESULT_MESSAGE, &tree));
+            if let Some((elapsed, args)) = foreground_command(&payload) {
+                let foreground = format!("{} {}", elapsed, args);
+                post_message_to_plugin(PluginMessage::new_to_plugin(
+                    FOREGROUND_MESSAGE,
+                    &foreground,
+                ));
+            }
+        } else if message == PARSE_IDLE_MESSAGE {
+            let idle_panes = idle_pane_ids(&payload)
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(" ");
+            post_message_to_plugin(PluginMessage::new_to_plugin(IDLE_MESSAGE, &idle_panes));
+        } else if message == PARSE_COMMANDS_MESSAGE {
+            let commands = pane_commands(&payload)
+                .iter()
+                .map(|(pane_id, children, args)| format!("{} {} {}", pane_id, children, args))
+                .collect::<Vec<_>>()
+                .join("\n");
+            post_message_to_plugin(PluginMessage::new_to_plugin(COMMANDS_MESSAGE, &commands));
+        }
+    }
+}
+
+/// A single line of `ps -eo pid=,ppid=,pgid=,tpgid=,etimes=,args=` output
+struct Process<'a> {
+    pid: u32,
+    ppid: u32,
+    /// The process group, and the foreground process group of its terminal
+    pgid: u32,
+    tpgid: i64,
+    /// Seconds since the process started
+    elapsed: u64,
+    args: &'a str,
+}
+
+impl<'a> Process<'a> {
+    fn from_ps_line(line: &'a str) -> Option<Self> {
+        let mut rest = line.trim();
+        let mut fields = [""; 5];
+        for field in fields.iter_mut() {
+            let (value, remaining) = rest.split_once(char::is_whitespace)?;
+            *field = value;
+            rest = remaining.trim_start();
+        }
+        Some(Process {
+            pid: fields[0].parse().ok()?,
+            ppid: fields[1].parse().ok()?,
+            pgid: fields[2].parse().ok()?,
+            tpgid: fields[3].parse().ok()?,
+            elapsed: fields[4].parse().ok()?,
+            args: rest.trim_end(),
+        })
+    }
+
+    fn is_foreground(&self) -> bool {
+        i64::from(self.pgid) == self.tpgid
+    }
+}
+
+/// Parse the `root <pid>` lines and the `ps` lines of the script output
+fn parse_output(output: &str) -> (Vec<u32>, Vec<Process<'_>>) {
+    let roots = output
+        .lines()
+        .filter_map(|line| line.strip_prefix("root ")?.trim().parse().ok())
+        .collect();
+    let processes = output
+        .lines()
+        .filter(|line| !line.starts_with("root "))
+        .filter_map(Process::from_ps_line)
+        .collect();
+    (roots, processes)
+}
+
+/// Every process below the roots, parents first, along with its depth below its root
+fn descendants<'a, 'b>(
+    roots: &[u32],
+    processes: &'b [Process<'a>],
+) -> Vec<(&'b Process<'a>, usize)> {
+    let mut descendants = Vec::new();
+    // Depth-first, with the depth alongside each pid still to visit
+    let mut stack: Vec<(u32, usize)> = roots.iter().rev().map(|pid| (*pid, 0)).collect();
+    while let Some((pid, depth)) = stack.pop() {
+        let Some(process) = processes.iter().find(|process| process.pid == pid) else {
+            continue;
+        };
+        descendants.push((process, depth));
+        stack.extend(
+            processes
+                .iter()
+                .rev()
+                .filter(|child| child.ppid == pid)
+                .map(|child| (child.pid, depth + 1)),
+        );
+    }
+    descendants
+}
+
+/// The processes below every root listed in the script output, parents first and children
+/// indented by depth, as `args (pid)`
+fn process_tree(output: &str) -> Vec<String> {
+    let (roots, processes) = parse_output(output);
+    descendants(&roots, &processes)
+        .into_iter()
+        .map(|(process, depth)| format!("{}{} ({})", "  ".repeat(depth), process.args, process.pid))
+        .collect()
+}
+
+/// The elapsed seconds and args of the command in the foreground of the pane's terminal, if
+/// something other than the pane's shell is
+fn foreground_command(output: &str) -> Option<(u64, String)> {
+    let (roots, processes) = parse_output(output);
+    descendants(&roots, &processes)
+        .into_iter()
+        .find(|(process, depth)| *depth > 0 && process.is_foreground())
+        .map(|(process, _)| (process.elapsed, process.args.to_string()))
+}
+
+/// Parse the `pane <pane id> <pid>` lines of `PANES_SCRIPT` output
+fn pane_roots(output: &str) -> Vec<(u32, u32)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (pane_id, pid) = line.strip_prefix("pane ")?.split_once(' ')?;
+            Some((pane_id.parse().ok()?, pid.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// The ids of the panes whose first process has no processes below it, meaning nothing was
+/// started from it, not even a background job
+fn idle_pane_ids(output: &str) -> Vec<u32> {
+    let processes: Vec<Process> = output.lines().filter_map(Process::from_ps_line).collect();
+    pane_roots(output)
+        .into_iter()
+        .filter(|(_, pid)| {
+            let descendants = descendants(&[*pid], &processes);
+            !descendants.is_empty() && descendants.iter().all(|(_, depth)| *depth == 0)
+        })
+        .map(|(pane_id, _)| pane_id)
+        .collect()
+}
+
+/// How many processes run below every pane's first process, background jobs included, and the
+/// command line in the foreground of its terminal: the same one `foreground_command` finds, or
+/// the pane's first process (its shell) when nothing else is
+fn pane_commands(output: &str) -> Vec<(u32, usize, String)> {
+    let processes: Vec<Process> = output.lines().filter_map(Process::from_ps_line).collect();
+    pane_roots(output)
+        .into_iter()
+        .filter_map(|(pane_id, pid)| {
+            let descendants = descendants(&[pid], &processes);
+            let (process, _) = descendants
+                .iter()
+                .find(|(process, depth)| *depth > 0 && process.is_foreground())
+                .or_else(|| descendants.first())?;
+            Some((pane_id, descendants.len() - 1, process.args.to_string()))
+        })
+        .collect()
+}
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+
+use crate::{command_name, Action};
+
+/// What a matching rule does to the dialog
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuleOutcome {
+    /// Execute the action right away, without the dialog
+    Skip,
+    /// Show the regular dialog
+    Confirm,
+    /// Require an escalated (double press) confirmation
+    Escalate,
+    /// Require typing out the confirmation word
+    TypedConfirm,
+}
+
+impl RuleOutcome {
+    fn from_config(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "skip" => Some(RuleOutcome::Skip),
+            "confirm" => Some(RuleOutcome::Confirm),
+            "escalate" | "double_confirm" => Some(RuleOutcome::Escalate),
+            "typed_confirm" => Some(RuleOutcome::TypedConfirm),
+            _ => None,
+        }
+    }
+}
+
+/// A single `key=pattern` condition of a rule, patterns may use `*` and `?` wildcards
+#[derive(Debug, Clone, PartialEq)]
+enum RuleCondition {
+    /// The executable name of the command running in the target pane
+    Command(String),
+    /// The name of the target tab
+    Tab(String),
+    /// The configured action, e.g. `close_pane`
+    Action(Action),
+}
+
+impl RuleCondition {
+    fn from_config(s: &str) -> Option<Self> {
+        let (key, pattern) = s.split_once('=')?;
+        let pattern = pattern.trim().to_string();
+        match key.trim().to_lowercase().as_str() {
+            "command" => Some(RuleCondition::Command(pattern)),
+            "tab" => Some(RuleCondition::Tab(pattern)),
+            "action" => Some(RuleCondition::Action(Action::from_config(&pattern))),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, target: &RuleTarget) -> bool {
+        match self {
+            RuleCondition::Command(pattern) => target
+                .command
+                .is_some_and(|command| glob_match(pattern, command_name(command))),
+            RuleCondition::Tab(pattern) => target
+                .tab_name
+                .is_some_and(|tab_name| glob_match(pattern, tab_name)),
+            RuleCondition::Action(action) => action == target.action,
+        }
+    }
+}
+
+/// What rules are evaluated against
+pub struct RuleTarget<'a> {
+    pub action: &'a Action,
+    pub command: Option<&'a str>,
+    pub tab_name: Option<&'a str>,
+}
+
+/// A configured rule, e.g. `rule_1 "command=vim => escalate"`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    /// The configuration key the rule came from, shown when it escalates the dialog
+    pub name: String,
+    /// Conditions that must all match, separated by commas in configuration
+    conditions: Vec<RuleCondition>,
+    pub outcome: RuleOutcome,
+}
+
+impl Rule {
+    pub(crate) fn from_config(name: &str, s: &str) -> Option<Self> {
+        let (conditions, outcome) = s.split_once("=>")?;
+        let conditions = conditions
+            .split(',')
+            .map(str::trim)
+            .filter(|condition| !condition.is_empty())
+            .map(RuleCondition::from_config)
+            .collect::<Option<Vec<_>>>()?;
+        Some(Rule {
+            name: name.to_string(),
+            conditions,
+            outcome: RuleOutcome::from_config(outcome.trim())?,
+        })
+    }
+
+    fn matches(&self, target: &RuleTarget) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.matches(target))
+    }
+}
+
+/// Parse every `rule_<n>` configuration key, in numeric order, skipping invalid rules
+pub fn rules_from_config(configuration: &BTreeMap<String, String>) -> Vec<Rule> {
+    let mut numbered_rules: Vec<(usize, Rule)> = configuration
+        .iter()
+        .filter_map(|(key, value)| {
+            let number = key.strip_prefix("rule_")?.parse().ok()?;
+            Some((number, Rule::from_config(key, value)?))
+        })
+        .collect();
+    numbered_rules.sort_by_key(|(number, _)| *number);
+    numbered_rules.into_iter().map(|(_, rule)| rule).collect()
+}
+
+/// The first rule matching the target, if any
+pub fn first_match<'a>(rules: &'a [Rule], target: &RuleTarget) -> Option<&'a Rule> {
+    rules.iter().find(|rule| rule.matches(target))
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters and `?` any
+/// single character
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was seen, and how much text it had consumed, for backtracking
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
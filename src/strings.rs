@@ -0,0 +1,387 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// Every string of the dialog's own that a `string.<id>` configuration key can replace, with its
+/// English default. `{name}` placeholders are filled in where the string is shown, and most
+/// strings with a `{count}` come with an `<id>.plural` for counts other than one.
+const DEFAULTS: &[(&str, &str)] = &[
+    ("title.quit_session", "Quit Session"),
+    ("title.detach_session", "Detach Session"),
+    ("title.kill_all_sessions", "Kill All Sessions"),
+    ("title.kill_session", "Kill Session"),
+    ("title.kill_and_delete_session", "Kill And Delete Session"),
+    ("title.close_pane", "Close Pane"),
+    ("title.close_other_panes", "Close Other Panes"),
+    ("title.restart_pane", "Restart Pane"),
+    ("title.close_floating_panes", "Close Floating Panes"),
+    ("title.close_exited_panes", "Close Exited Panes"),
+    ("title.interrupt_all", "Stop All Commands"),
+    ("title.close_tab", "Close Tab"),
+    ("title.close_other_tabs", "Close Other Tabs"),
+    ("title.close_tabs_to_right", "Close Tabs To The Right"),
+    ("title.dump_layout", "Dump Layout"),
+    ("title.close_idle_panes", "Close Idle Panes"),
+    ("title.chain", "Action Chain"),
+    ("title.confirm", "Confirm"),
+    (
+        "text.quit_session",
+        "Are you sure you want to quit this session?",
+    ),
+    (
+        "text.detach_session",
+        "Are you sure you want to detach from this session?",
+    ),
+    (
+        "text.kill_all_sessions",
+        "Are you sure you want to kill all running sessions?",
+    ),
+    (
+        "text.kill_session",
+        "Are you sure you want to kill this session?",
+    ),
+    (
+        "text.kill_and_delete_session",
+        "Are you sure you want to permanently delete this session? It cannot be resurrected.",
+    ),
+    (
+        "text.close_pane",
+        "Are you sure you want to close this pane?",
+    ),
+    (
+        "text.close_other_panes",
+        "Are you sure you want to close all other panes in this tab?",
+    ),
+    (
+        "text.restart_pane",
+        "Are you sure you want to restart this command?",
+    ),
+    (
+        "text.close_floating_panes",
+        "Are you sure you want to close all floating panes in this tab?",
+    ),
+    (
+        "text.close_exited_panes",
+        "Are you sure you want to close all exited command panes in this session?",
+    ),
+    (
+        "text.interrupt_all",
+        "Are you sure you want to stop all running commands?",
+    ),
+    ("text.close_tab", "Are you sure you want to close this tab?"),
+    (
+        "text.close_other_tabs",
+        "Are you sure you want to close all other tabs?",
+    ),
+    (
+        "text.close_tabs_to_right",
+        "Are you sure you want to close all tabs to the right?",
+    ),
+    (
+        "text.dump_layout",
+        "Are you sure you want to save the session layout?",
+    ),
+    (
+        "text.close_idle_panes",
+        "Are you sure you want to close the selected idle panes?",
+    ),
+    (
+        "text.chain",
+        "Are you sure you want to run all of these actions?",
+    ),
+    ("text.confirm", "Are you sure?"),
+    ("button.confirm", "Confirm"),
+    ("button.cancel", "Cancel"),
+    ("target", "Target:"),
+    ("target.detecting", "(detecting...)"),
+    ("target.scanning", "(scanning...)"),
+    ("warning", "Warning:"),
+    ("target.tab_crumb", "Tab {position} \"{name}\""),
+    ("target.unnamed_tab_crumb", "Tab {position}"),
+    ("target.pane_crumb", "pane #{id} ({command})"),
+    ("target.bare_pane_crumb", "pane #{id}"),
+    ("target.plugin_crumb", "plugin pane #{id}"),
+    ("target.command_pane", "Command pane #{id} ({command})"),
+    ("target.exited", " exited"),
+    ("target.exit_code", " with code {code}"),
+    ("target.runtime", " after {duration}"),
+    ("target.held", ", held"),
+    ("target.in_stack", "{pane} (in a stack of {count} panes)"),
+    ("target.other_panes", "{count} other pane"),
+    ("target.other_panes.plural", "{count} other panes"),
+    (
+        "target.terminal_pane",
+        "Terminal pane #{id} (not a command pane)",
+    ),
+    ("target.floating_panes", "{count} floating pane"),
+    ("target.floating_panes.plural", "{count} floating panes"),
+    ("target.exited_panes", "{count} exited command pane"),
+    ("target.exited_panes.plural", "{count} exited command panes"),
+    ("target.tab_panes", "{count} terminal pane in this tab"),
+    (
+        "target.tab_panes.plural",
+        "{count} terminal panes in this tab",
+    ),
+    (
+        "target.session_panes",
+        "{count} terminal pane in this session",
+    ),
+    (
+        "target.session_panes.plural",
+        "{count} terminal panes in this session",
+    ),
+    ("target.tab", "tab '{name}'"),
+    ("target.tab_number", "Tab #{position}"),
+    ("target.other_tabs", "{count} other tab"),
+    ("target.other_tabs.plural", "{count} other tabs"),
+    ("target.no_tabs_to_right", "no tabs to the right"),
+    ("target.tabs_to_right", "{count} tab ({names})"),
+    ("target.tabs_to_right.plural", "{count} tabs ({names})"),
+    ("target.sessions", "{count} running session"),
+    ("target.sessions.plural", "{count} running sessions"),
+    ("target.session", "Session '{name}'"),
+    (
+        "target.current_session",
+        "Session '{name}' (current session, will not be killed)",
+    ),
+    (
+        "target.session_not_running",
+        "Session '{name}' (not running)",
+    ),
+    ("target.no_session_name", "(no session_name configured)"),
+    (
+        "target.idle_panes",
+        "{count} idle pane, {selected} selected",
+    ),
+    (
+        "target.idle_panes.plural",
+        "{count} idle panes, {selected} selected",
+    ),
+    (
+        "remote_connection",
+        "This drops the remote connection of '{name}'",
+    ),
+    (
+        "warning.last_tab",
+        "this is the last tab, closing it ends the session",
+    ),
+    ("warning.editor", "'{editor}' may have unsaved changes"),
+    ("warning.uncommitted", "uncommitted changes in {repo}"),
+    (
+        "warning.other_clients",
+        "{count} other client is attached to this session",
+    ),
+    (
+        "warning.other_clients.plural",
+        "{count} other clients are attached to this session",
+    ),
+    ("escalation.rule", "Escalated by {rule}"),
+    (
+        "escalation.danger_command",
+        "The target pane is running '{command}'",
+    ),
+    (
+        "escalation.long_running",
+        "'{command}' has been running for {duration}",
+    ),
+    (
+        "escalation.large_session",
+        "This session has {tabs} and {panes}",
+    ),
+    ("count.tab", "{count} tab"),
+    ("count.tab.plural", "{count} tabs"),
+    ("count.pane", "{count} pane"),
+    ("count.pane.plural", "{count} panes"),
+    ("auto_confirm", "Auto-confirm in {seconds}s{ellipsis}"),
+    ("auto_cancel", "Auto-cancel in {seconds}s{ellipsis}"),
+    ("tab_table.tab", "Tab"),
+    ("tab_table.panes", "Panes"),
+    ("tab_table.plugin", "{entry} (plugin)"),
+    ("more", "+{count} more"),
+    ("hint.confirm", "confirm"),
+    ("hint.cancel", "cancel"),
+    ("hint.detach", "detach"),
+    ("hint.float", "float"),
+    ("hint.close_stack", "close stack"),
+    ("hint.interrupt", "interrupt"),
+    ("hint.disconnect_others", "disconnect others"),
+    ("hint.move", "move"),
+    ("hint.toggle", "toggle"),
+    ("hint.scroll", "scroll"),
+    ("hint.select", "select"),
+    ("hint.press", "press"),
+    (
+        "key_hint",
+        "Press {confirm} to confirm or {cancel} to cancel",
+    ),
+    (
+        "armed",
+        "Press {confirm} or {enter} again to confirm, {cancel} to cancel",
+    ),
+    (
+        "typed_prompt",
+        "Type '{word}' and press {enter} to confirm, {cancel} to cancel",
+    ),
+    (
+        "armed_alternative",
+        "Press {key} again to {alternative}, {cancel} to cancel",
+    ),
+    ("compact", "{confirm}=yes {cancel}=no"),
+    ("compact.armed", "{confirm} again=yes {cancel}=no"),
+    (
+        "compact.armed_alternative",
+        "{key} again={alternative} {cancel}=no",
+    ),
+    ("compact.typed", "type '{word}': {input}_ {cancel}=no"),
+    (
+        "quitting",
+        "Quitting in {seconds}{ellipsis} {dash} press any key to abort",
+    ),
+    (
+        "interrupting",
+        "Interrupting pane {done} of {total} (#{pane})",
+    ),
+    ("result.closed_pane", "Closed pane #{id}"),
+    ("result.closed_panes", "Closed {count} pane"),
+    ("result.closed_panes.plural", "Closed {count} panes"),
+    ("result.closed_tabs", "Closed {count} tab"),
+    ("result.closed_tabs.plural", "Closed {count} tabs"),
+    (
+        "result.closed_tab",
+        "Closed tab '{name}' and its {count} pane",
+    ),
+    (
+        "result.closed_tab.plural",
+        "Closed tab '{name}' and its {count} panes",
+    ),
+    (
+        "error.permissions_denied",
+        "The permissions needed to run this action were denied",
+    ),
+    (
+        "error.dump_layout",
+        "Could not dump the session layout: {error}",
+    ),
+    (
+        "error.write_layout",
+        "Could not write layout to {file}: {error}",
+    ),
+    (
+        "error.delete_session",
+        "Could not delete session '{name}': {error}. Dismissing this quits it instead, leaving \
+         it resurrectable",
+    ),
+    ("error.no_session_name", "No session_name configured"),
+    (
+        "error.session_not_killable",
+        "Session '{name}' is not running or is the current session",
+    ),
+    ("error.no_target_pane", "Could not tell which pane to close"),
+    ("error.pane_gone", "Pane #{id} no longer exists"),
+    (
+        "error.not_command_pane",
+        "The target pane is not a command pane",
+    ),
+    ("error.tab_gone", "The tab to close no longer exists"),
+    ("error.no_idle_panes", "No idle panes selected"),
+    ("error", "Error"),
+    ("done", "Done"),
+    ("config_problems", "Configuration problems"),
+    ("dismiss", "Press any key to dismiss"),
+];
+
+/// Whether `id` names one of the strings in `DEFAULTS`
+pub fn is_known(id: &str) -> bool {
+    DEFAULTS.iter().any(|(default_id, _)| *default_id == id)
+}
+
+/// The dialog's strings: the English defaults, with any `string.<id>` overrides
+#[derive(Debug, Default)]
+pub struct Strings {
+    overrides: BTreeMap<String, String>,
+}
+
+impl Strings {
+    pub fn from_config(configuration: &BTreeMap<String, String>) -> Self {
+        let overrides = configuration
+            .iter()
+            .filter_map(|(key, value)| {
+                Some((key.strip_prefix("string.")?.to_string(), value.clone()))
+            })
+            .collect();
+        Strings { overrides }
+    }
+
+    /// The string `id`, or the id itself if there is no such string
+    pub fn get<'a>(&'a self, id: &'a str) -> &'a str {
+        self.overrides
+            .get(id)
+            .map(String::as_str)
+            .or_else(|| {
+                DEFAULTS
+                    .iter()
+                    .find(|(default_id, _)| *default_id == id)
+                    .map(|(_, text)| *text)
+            })
+            .unwrap_or(id)
+    }
+
+    /// The string `id` with its placeholders filled in from `values`
+    pub fn format(&self, id: &str, values: &[(&str, &str)]) -> String {
+        self.format_with_ranges(id, values).0
+    }
+
+    /// The string `id` for a count of one, or `id.plural` for any other, with `{count}` and the
+    /// rest of its placeholders filled in
+    pub fn format_count(&self, id: &str, count: usize, values: &[(&str, &str)]) -> String {
+        let id = if count == 1 {
+            id.to_string()
+        } else {
+            format!("{}.plural", id)
+        };
+        let count = count.to_string();
+        let mut values = values.to_vec();
+        values.push(("count", &count));
+        self.format(&id, &values)
+    }
+
+    /// The string `id` with its placeholders filled in like `format`, along with the character
+    /// range of every filled-in value, for coloring them
+    pub fn format_with_ranges(
+        &self,
+        id: &str,
+        values: &[(&str, &str)],
+    ) -> (String, Vec<Range<usize>>) {
+        fill(self.get(id), values)
+    }
+}
+
+/// Fill in the `{name}` placeholders of a template from `values`, returning the character range
+/// of every filled-in value along with the text
+pub fn fill(template: &str, values: &[(&str, &str)]) -> (String, Vec<Range<usize>>) {
+    let mut text = String::new();
+    let mut ranges = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let placeholder = after.find('}').and_then(|end| {
+            let (_, value) = values.iter().find(|(name, _)| *name == &after[..end])?;
+            Some((end, value))
+        });
+        match placeholder {
+            Some((end, value)) => {
+                let value_start = text.chars().count();
+                text.push_str(value);
+                ranges.push(value_start..value_start + value.chars().count());
+                rest = &after[end + 1..];
+            }
+            // Not one of ours, kept as typed
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    (text, ranges)
+}